@@ -8,7 +8,48 @@
 /////////////
 
 #let areas = (Großforschung: "GF", Unibereich: "UB")
+#let languages = (German: "de", English: "en", Bilingual: "de-en")
 #let notes = (Urlaub: "U", Krankheit: "K", Feiertag: "F", Sonstiges: "S")
+#let states = ("BW", "BY", "BE", "BB", "HB", "HH", "HE", "MV", "NI", "NW", "RP", "SL", "SN", "ST", "SH", "TH")
+
+//////////////
+// Language //
+//////////////
+
+// A label in the document. Bilingual labels have the English translation in
+// smaller text after the German one.
+#let _label(language, de, en) = {
+  if language == languages.German {
+    de
+  } else if language == languages.English {
+    en
+  } else {
+    [#de #text(size: 0.75em, weight: "regular", en)]
+  }
+}
+
+// Like `_label`, but bilingual labels have the English translation below the
+// German one. It doesn't take up any space, so there must be room below.
+#let _label_below(language, de, en) = {
+  if language == languages.German {
+    de
+  } else if language == languages.English {
+    en
+  } else {
+    box(de + place(bottom + left, dy: 0.6em, text(size: 0.6em, weight: "regular", en)))
+  }
+}
+
+// A validation message.
+#let _message(language, de, en) = {
+  if language == languages.German {
+    de
+  } else if language == languages.English {
+    en
+  } else {
+    de + " / " + en
+  }
+}
 
 ////////////
 // Layout //
@@ -18,8 +59,9 @@
 #let _kit_stroke = 0.2mm
 #let _kit_rows = 22
 
-#let _frame(body) = {
-  set text(lang: "de", font: "Liberation Sans")
+#let _frame(language, body) = {
+  let lang = if language == languages.English { "en" } else { "de" }
+  set text(lang: lang, font: "Liberation Sans")
   set page(margin: (top: 9.5mm, bottom: 12mm, left: 15mm, right: 10mm))
   set block(spacing: 1mm)
   set par(leading: 5pt)
@@ -50,14 +92,14 @@
     #_compose(
       place.with(top + left, dx: 78mm, dy: 9mm),
       text.with(weight: "bold", size: 14pt, fill: _kit_green),
-      [Arbeitszeitdokumentation],
+      _label_below(language, [Arbeitszeitdokumentation], [Working Time Record]),
     )
 
     // Page number
     #_compose(
       place.with(bottom + right, dx: -15mm, dy: -1.5mm),
       text.with(size: 9pt),
-      [Seite 1 von 1],
+      _label(language, [Seite 1 von 1], [Page 1 of 1]),
     )
 
     // Main content
@@ -86,6 +128,7 @@
 )
 
 #let _header(
+  language,
   year: " ",
   month: " ",
   name: " ",
@@ -97,6 +140,7 @@
 ) = {
   // "OE" means "Institut / Organisationseinheit"
   set text(size: 11pt)
+  let l = _label_below.with(language)
   pad(
     left: 2.5mm,
     grid(
@@ -104,7 +148,7 @@
       rows: 6mm,
       [],
       align(right)[
-        *Monat / Jahr:* #h(6mm)
+        #l[*Monat / Jahr:*][*Month / Year:*] #h(6mm)
         #_compose(
           _underlined,
           align.with(center),
@@ -112,8 +156,9 @@
         )
       ],
 
-      [*Name, Vorname des/r Beschäftigten:*], _underlined(box(width: 100%, name)),
-      [*Personalnummer:*],
+      l[*Name, Vorname des/r Beschäftigten:*][*Employee's name:*],
+      _underlined(box(width: 100%, name)),
+      l[*Personalnummer:*][*Staff ID:*],
       _underlined[
         #box(width: 1fr)[#staff_id]
         #h(8mm)
@@ -125,16 +170,16 @@
         #h(8mm)
       ],
 
-      [*OE:*], _underlined(box(width: 100%)[#department]),
-      [*Vertraglich vereinbarte Arbeitszeit:*],
+      l[*OE:*][*Unit:*], _underlined(box(width: 100%)[#department]),
+      l[*Vertraglich vereinbarte Arbeitszeit:*][*Contractual working time:*],
       [
         #_compose(
           _underlined,
           align.with(center),
-          [#h(4mm) #box(width: 10mm)[#monthly_hours] Std. #h(4mm)],
+          [#h(4mm) #box(width: 10mm)[#monthly_hours] #_label(language)[Std.][h] #h(4mm)],
         )
         #h(1fr)
-        *Stundensatz:*
+        #l[*Stundensatz:*][*Hourly wage:*]
         #h(4mm)
         #_compose(
           _underlined,
@@ -146,8 +191,30 @@
   )
 }
 
-#let _log(..entries) = {
+#let _log(language, ..entries) = {
   set text(size: 10pt)
+  // Bilingual headers have the English labels in the second row instead of the
+  // formats, which are the same in both languages anyway.
+  let header = if language == languages.German {
+    (
+      [*Tätigkeit* \ *(Stichwort, Projekt)*],
+      ([*Datum*], [*Beginn*], [*Ende*], [*Pause*], [*Arbeitszeit#super[1]*]),
+      ([*(tt.mm.jj)*], [*(hh:mm)*], [*(hh:mm)*], [*(hh:mm)*], [*(hh:mm)*]),
+    )
+  } else if language == languages.English {
+    (
+      [*Task* \ *(keyword, project)*],
+      ([*Date*], [*Start*], [*End*], [*Break*], [*Working time#super[1]*]),
+      ([*(dd.mm.yy)*], [*(hh:mm)*], [*(hh:mm)*], [*(hh:mm)*], [*(hh:mm)*]),
+    )
+  } else {
+    (
+      [*Tätigkeit (Stichwort, Projekt)* \ #text(size: 8pt)[Task (keyword, project)]],
+      ([*Datum*], [*Beginn*], [*Ende*], [*Pause*], [*Arbeitszeit#super[1]*]),
+      ("Date", "Start", "End", "Break", [Working time#super[1]]).map(text.with(size: 8pt)),
+    )
+  }
+
   table(
     columns: (1fr, 23.3mm, 23.3mm, 23.3mm, 23.3mm, 23.3mm),
     rows: array.range(_kit_rows + 2).map(_ => 5.05mm),
@@ -155,26 +222,16 @@
     stroke: _kit_stroke,
     inset: 1mm,
     table.header(
-      table.cell(rowspan: 2)[
-        *Tätigkeit* \
-        *(Stichwort, Projekt)*
-      ],
-      [*Datum*],
-      [*Beginn*],
-      [*Ende*],
-      [*Pause*],
-      [*Arbeitszeit#super[1]*],
-      [*(tt.mm.jj)*],
-      [*(hh:mm)*],
-      [*(hh:mm)*],
-      [*(hh:mm)*],
-      [*(hh:mm)*],
+      table.cell(rowspan: 2, header.at(0)),
+      ..header.at(1),
+      ..header.at(2),
     ),
     ..entries.pos()
   )
 }
 
 #let _summary(
+  language,
   holiday: [],
   total: [],
   monthly_hours: [],
@@ -182,27 +239,35 @@
   carry_next_month: [],
 ) = {
   set text(size: 10pt)
+  let l = _label.with(language)
   align(
     right,
     table(
-      columns: (54mm, 23.3mm),
+      columns: (if language == languages.Bilingual { 90mm } else { 54mm }, 23.3mm),
       rows: 5.05mm,
       align: center + horizon,
       stroke: _kit_stroke,
       inset: 1mm,
-      [*Urlaub anteilig:*], [#holiday],
-      [*Summe:*], [#total],
-      [*monatliche Soll-Arbeitszeit:*], [#monthly_hours],
-      [*Übertrag vom Vormonat:*], [#carry_prev_month],
-      [*Übertrag in den Folgemonat:*], [#carry_next_month],
+      l[*Urlaub anteilig:*][*Pro-rata vacation:*], [#holiday],
+      l[*Summe:*][*Total:*], [#total],
+      l[*monatliche Soll-Arbeitszeit:*][*Monthly working time:*], [#monthly_hours],
+      l[*Übertrag vom Vormonat:*][*Carried over from last month:*], [#carry_prev_month],
+      l[*Übertrag in den Folgemonat:*][*Carried over to next month:*], [#carry_next_month],
     ),
   )
 }
 
-#let _footer() = pad(left: 2.5mm)[
+#let _footer(
+  language,
+  signature_date: none,
+  signature: none,
+  supervisor_name: none,
+  supervisor_date: none,
+  supervisor_signature: none,
+) = pad(left: 2.5mm)[
   #v(3.5mm)
 
-  #let signature = pad(
+  #let signature_line = pad(
     left: -2.5mm,
     line(
       length: 100%,
@@ -210,30 +275,79 @@
     ),
   )
 
+  #let signed(date: none, name: none, signature: none) = {
+    let label = (if date != none { date.display("[day].[month].[year]") }, name).filter(x => x != none)
+    if label.len() > 0 {
+      place(bottom + left, dy: -1mm, label.join(", "))
+    }
+    if signature != none {
+      place(bottom + center, dy: -0.5mm, signature)
+    }
+    signature_line
+  }
+
+  #let l = _label_below.with(language)
+
   #grid(
     columns: (1fr, 77.5mm),
     column-gutter: 6.5mm,
     row-gutter: (12mm, 3mm),
-    [Ich bestätige die Richtigkeit der Angaben:], [Geprüft:],
-    signature, signature,
-    [Datum, Unterschrift Beschäftigte/r], [Datum, Unterschrift Dienstvorgesetzte/r],
+    l[Ich bestätige die Richtigkeit der Angaben:][I confirm that the information is correct:],
+    l[Geprüft:][Checked:],
+    signed(date: signature_date, signature: signature),
+    signed(date: supervisor_date, name: supervisor_name, signature: supervisor_signature),
+    l[Datum, Unterschrift Beschäftigte/r][Date, signature of employee],
+    l[Datum, Unterschrift Dienstvorgesetzte/r][Date, signature of supervisor],
   )
 
 
   #v(5.5mm)
 
-  #set text(size: 10pt)
-  Nach *§ 17 Mindestlohngesetz (MiLoG)* müssen für geringfügig entlohnte und kurzfristig beschäftigte
-  Arbeitnehmer/innen u.a. Beginn, Ende und Dauer der täglichen Arbeitszeit aufgezeichnet und für Kon-
-  trollzwecke mindestens zwei Jahre am Ort der Beschäftigung aufbewahrt werden.
+  #let milog_de = [
+    Nach *§ 17 Mindestlohngesetz (MiLoG)* müssen für geringfügig entlohnte und kurzfristig beschäftigte
+    Arbeitnehmer/innen u.a. Beginn, Ende und Dauer der täglichen Arbeitszeit aufgezeichnet und für Kon-
+    trollzwecke mindestens zwei Jahre am Ort der Beschäftigung aufbewahrt werden.
+  ]
+  #let milog_en = [
+    According to *§ 17 of the Minimum Wage Act (MiLoG)*, the start, end and duration of the daily
+    working time of marginally and short-term employed staff must be recorded and kept at the place of
+    employment for at least two years for inspection purposes.
+  ]
+  #let footnote_de = [
+    #super[1] Summe in vollen Stunden und Minuten ohne Pause (Std:Min); bei Abwesenheit können auch folgende Kürzel
+    eingetragen werden: U=Urlaub, K=Krankheit, F=Feiertag, S=Sonstiges
+  ]
+  #let footnote_en = [
+    #super[1] Total in full hours and minutes without breaks (hh:mm); in case of absence, the following
+    abbreviations can be used: U=vacation, K=sickness, F=public holiday, S=other
+  ]
 
-  #v(12.5mm)
+  // There's not enough room for both languages at full size, so the English
+  // translation is smaller and the gap above the footnote shrinks.
+  #set text(size: 10pt)
+  #if language == languages.German [
+    #milog_de
+    #v(12.5mm)
+  ] else if language == languages.English [
+    #milog_en
+    #v(12.5mm)
+  ] else [
+    #milog_de
+    #text(size: 7pt, milog_en)
+    #v(2mm)
+  ]
   #line(length: 51mm, stroke: _kit_stroke)
   #v(1mm)
 
   #set text(size: 9pt)
-  #super[1] Summe in vollen Stunden und Minuten ohne Pause (Std:Min); bei Abwesenheit können auch folgende Kürzel
-  eingetragen werden: U=Urlaub, K=Krankheit, F=Feiertag, S=Sonstiges
+  #if language == languages.English {
+    footnote_en
+  } else {
+    footnote_de
+  }
+  #if language == languages.Bilingual {
+    text(size: 7pt, footnote_en)
+  }
 ]
 
 //////////
@@ -291,65 +405,119 @@
   datetime(year: year, month: month, day: day)
 }
 
-#let _public_holidays_germany_bw(year) = {
+#let _public_holidays_germany(year, state) = {
+  assert(states.contains(state), message: "unknown state: " + state)
   let easter = _computus(year)
-  (
+  let in_states(..codes) = codes.pos().contains(state)
+
+  let holidays = (
     (name: "Neujahr", date: datetime(year: year, month: 1, day: 1)),
-    (name: "Heilige Drei Könige", date: datetime(year: year, month: 1, day: 6)),
     (name: "Karfreitag", date: easter - duration(days: 2)),
     (name: "Ostermontag", date: easter + duration(days: 1)),
     (name: "Tag der Arbeit", date: datetime(year: year, month: 5, day: 1)),
     (name: "Christi Himmelfahrt", date: easter + duration(days: 39)),
     (name: "Pfingstmontag", date: easter + duration(days: 50)),
-    (name: "Fronleichnam", date: easter + duration(days: 60)),
     (name: "Tag der Deutschen Einheit", date: datetime(year: year, month: 10, day: 3)),
-    (name: "Allerheiligen", date: datetime(year: year, month: 11, day: 1)),
     (name: "Erster Weihnachtsfeiertag", date: datetime(year: year, month: 12, day: 25)),
     (name: "Zweiter Weihnachtsfeiertag", date: datetime(year: year, month: 12, day: 26)),
   )
+
+  if in_states("BW", "BY", "ST") {
+    holidays.push((name: "Heilige Drei Könige", date: datetime(year: year, month: 1, day: 6)))
+  }
+  if (state == "BE" and year >= 2019) or (state == "MV" and year >= 2023) {
+    holidays.push((name: "Internationaler Frauentag", date: datetime(year: year, month: 3, day: 8)))
+  }
+  if in_states("BW", "BY", "HE", "NW", "RP", "SL") {
+    holidays.push((name: "Fronleichnam", date: easter + duration(days: 60)))
+  }
+  if state == "SL" {
+    holidays.push((name: "Mariä Himmelfahrt", date: datetime(year: year, month: 8, day: 15)))
+  }
+  if state == "TH" and year >= 2019 {
+    holidays.push((name: "Weltkindertag", date: datetime(year: year, month: 9, day: 20)))
+  }
+  if (
+    year == 2017
+      or in_states("BB", "MV", "SN", "ST", "TH")
+      or (in_states("HB", "HH", "NI", "SH") and year >= 2018)
+  ) {
+    holidays.push((name: "Reformationstag", date: datetime(year: year, month: 10, day: 31)))
+  }
+  if in_states("BW", "BY", "NW", "RP", "SL") {
+    holidays.push((name: "Allerheiligen", date: datetime(year: year, month: 11, day: 1)))
+  }
+  if state == "SN" {
+    // The last Wednesday before November 23
+    let nov22 = datetime(year: year, month: 11, day: 22)
+    let offset = calc.rem(nov22.weekday() - 3 + 7, 7)
+    holidays.push((name: "Buß- und Bettag", date: nov22 - duration(days: offset)))
+  }
+
+  holidays
 }
 
 ////////////////
 // Validation //
 ////////////////
 
-#let _assert_entry(row, entry, condition, message) = {
-  message = "row " + str(row) + " (day " + str(entry.day) + "): " + message
-  assert(condition, message: message)
+// Messages start with "row 3 (day 5): " or "day 5: ", or their German
+// equivalents "Zeile 3 (Tag 5): " and "Tag 5: ". The web server relies on this
+// to highlight the affected rows.
+
+#let _assert_entry(language, row, entry, condition, de, en) = {
+  let day = str(entry.day)
+  de = "Zeile " + str(row) + " (Tag " + day + "): " + de
+  en = "row " + str(row) + " (day " + day + "): " + en
+  assert(condition, message: _message(language, de, en))
 }
 
-#let _check_entries(year, month, entries) = {
+#let _check_entries(language, year, month, state, entries) = {
+  let check = _assert_entry.with(language)
   for (row, e) in entries.enumerate(start: 1) {
-    _assert_entry(row, e, e.start <= e.end, "start must be before end")
-    _assert_entry(row, e, e.rest <= e.end - e.start, "rest too long")
+    check(row, e, e.start <= e.end, "Beginn muss vor Ende liegen", "start must be before end")
+    check(row, e, e.rest <= e.end - e.start, "Pause zu lang", "rest too long")
 
     // I think the previous two checks should make it impossible for this assert
     // to fail, but just to be careful...
-    _assert_entry(row, e, e.duration.seconds() >= 0, "duration must be positive")
+    check(
+      row,
+      e,
+      e.duration.seconds() >= 0,
+      "Arbeitszeit muss positiv sein",
+      "duration must be positive",
+    )
 
     // Date checks
     let date = datetime(year: year, month: month, day: e.day)
-    _assert_entry(row, e, date.weekday() != 6, "day is a Saturday")
-    _assert_entry(row, e, date.weekday() != 7, "day is a Sunday")
-    for holiday in _public_holidays_germany_bw(year) {
-      _assert_entry(row, e, date != holiday.date, "day is a holiday (" + holiday.name + ")")
+    check(row, e, date.weekday() != 6, "Tag ist ein Samstag", "day is a Saturday")
+    check(row, e, date.weekday() != 7, "Tag ist ein Sonntag", "day is a Sunday")
+    for holiday in _public_holidays_germany(year, state) {
+      check(
+        row,
+        e,
+        date != holiday.date,
+        "Tag ist ein Feiertag (" + holiday.name + ")",
+        "day is a holiday (" + holiday.name + ")",
+      )
     }
 
     // Time range checks
     // https://github.com/kit-sdq/TimeSheetGenerator/blob/2e80a56483832fb96087b8145c6cf311ec417c60/src/main/java/checker/MiLoGChecker.java#L30-L31
     let earliest = _parse_duration("06:00")
     let latest = _parse_duration("22:00")
-    _assert_entry(row, e, e.start >= earliest, "must not work before 06:00")
-    _assert_entry(row, e, e.end <= latest, "must not work after 22:00")
+    check(row, e, e.start >= earliest, "Arbeit vor 06:00 nicht erlaubt", "must not work before 06:00")
+    check(row, e, e.end <= latest, "Arbeit nach 22:00 nicht erlaubt", "must not work after 22:00")
   }
 }
 
-#let _assert_day(day, condition, message) = {
-  message = "day " + str(day) + ": " + message
-  assert(condition, message: message)
+#let _assert_day(language, day, condition, de, en) = {
+  de = "Tag " + str(day) + ": " + de
+  en = "day " + str(day) + ": " + en
+  assert(condition, message: _message(language, de, en))
 }
 
-#let _check_days(entries) = {
+#let _check_days(language, entries) = {
   let by_day = (:)
   for entry in entries {
     let key = str(entry.day)
@@ -378,8 +546,10 @@
     // least headaches in the long run.
     let max_duration = _parse_duration("08:00")
     _assert_day(
+      language,
       day,
       info.duration <= max_duration,
+      "höchstens 8 Stunden Arbeit pro Tag erlaubt (Details im Kommentar im typst-Template)",
       "must not work more than 8 hours per day (see comment in typst template for more details)",
     )
 
@@ -399,34 +569,43 @@
     // limit is ever increased again.
     if info.duration > _parse_duration("09:00") {
       _assert_day(
+        language,
         day,
         info.rest >= _parse_duration("00:45"),
+        "mindestens 45 Minuten Pause nach mehr als 9 Stunden Arbeit nötig",
         "at least 45 minutes rest required after more than 9 hours of work",
       )
     } else if info.duration > _parse_duration("06:00") {
       _assert_day(
+        language,
         day,
         info.rest >= _parse_duration("00:30"),
+        "30 Minuten Pause nach mehr als 6 Stunden Arbeit nötig",
         "30 minutes rest required after more than 6 hours of work",
       )
     }
   }
 }
 
-#let _check_total(total) = {
+#let _check_total(language, total) = {
   let max_total = _parse_duration("85:00")
-  assert(total <= max_total, message: "must not work more than 85 hours per month")
+  let message = _message(
+    language,
+    "höchstens 85 Stunden Arbeit pro Monat erlaubt",
+    "must not work more than 85 hours per month",
+  )
+  assert(total <= max_total, message: message)
 }
 
 //////////////////
 // Entry points //
 //////////////////
 
-#let timesheet_empty() = _frame[
-  #_header()
-  #_log()
-  #_summary()
-  #_footer()
+#let timesheet_empty(language: languages.German) = _frame(language)[
+  #_header(language)
+  #_log(language)
+  #_summary(language)
+  #_footer(language)
 ]
 
 #let entry(
@@ -467,16 +646,35 @@
   carry_prev_month: "00:00",
   year: 2024,
   month: 1,
+  state: "BW",
+  signature_date: none,
+  signature: none,
+  supervisor_name: none,
+  supervisor_date: none,
+  supervisor_signature: none,
+  language: languages.German,
   ..entries,
 ) = {
+  assert(languages.values().contains(language))
   assert(working_area == none or areas.values().contains(working_area))
   assert(type(monthly_hours) == int)
   assert(type(year) == int)
   assert(type(month) == int)
+  assert(signature_date in (none, auto) or type(signature_date) == datetime)
+  assert(supervisor_date == none or type(supervisor_date) == datetime)
+
+  if signature_date == auto {
+    signature_date = datetime.today()
+  }
 
   carry_prev_month = _parse_duration(carry_prev_month)
   entries = entries.pos()
-  assert(entries.len() <= _kit_rows, message: "at most " + str(_kit_rows) + " entries allowed")
+  let message = _message(
+    language,
+    "höchstens " + str(_kit_rows) + " Einträge erlaubt",
+    "at most " + str(_kit_rows) + " entries allowed",
+  )
+  assert(entries.len() <= _kit_rows, message: message)
 
   if sort {
     entries = entries.sorted(key: entry => (entry.day, entry.end, entry.start))
@@ -488,9 +686,9 @@
   let carry_next_month = carry_prev_month + total - monthly
 
   if validate {
-    _check_entries(year, month, entries)
-    _check_days(entries)
-    _check_total(total)
+    _check_entries(language, year, month, state, entries)
+    _check_days(language, entries)
+    _check_total(language, total)
   }
 
   let rows = entries.map(e => (
@@ -508,8 +706,9 @@
     },
   ))
 
-  _frame[
+  _frame(language)[
     #_header(
+      language,
       year: year,
       month: month,
       name: name,
@@ -519,14 +718,22 @@
       monthly_hours: monthly_hours,
       hourly_wage: hourly_wage,
     )
-    #_log(..rows.flatten())
+    #_log(language, ..rows.flatten())
     #_summary(
+      language,
       holiday: _fmt_duration(holiday),
       total: _fmt_duration(total),
       monthly_hours: _fmt_duration(monthly),
       carry_prev_month: _fmt_duration(carry_prev_month),
       carry_next_month: _fmt_duration(carry_next_month),
     )
-    #_footer()
+    #_footer(
+      language,
+      signature_date: signature_date,
+      signature: signature,
+      supervisor_name: supervisor_name,
+      supervisor_date: supervisor_date,
+      supervisor_signature: supervisor_signature,
+    )
   ]
 }
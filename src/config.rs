@@ -0,0 +1,405 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use jiff::tz::TimeZone;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    limits::Limits,
+    render::{DEFAULT_HOLIDAY_STATE, DEFAULT_TIME_ZONE, HOLIDAY_STATES},
+};
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Endpoints {
+    /// The form at `/`, including preview, import and signature verification.
+    pub index: bool,
+    /// The TimeSheetGenerator-compatible JSON input at `/tsg/`.
+    pub tsg: bool,
+    /// Liveness and readiness checks at `/healthz` and `/readyz`.
+    pub health: bool,
+    /// Prometheus metrics at `/metrics`.
+    pub metrics: bool,
+    /// Submitting time sheets for review by a supervisor at `/submit`,
+    /// `/reviews/` and `/review/`. Requires `index`.
+    pub review: bool,
+    /// Compiling self-written typst files at `/compile`.
+    pub compile: bool,
+    /// Saving the form's header data on the server at `/profile`. Requires
+    /// `index`.
+    pub profiles: bool,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            index: true,
+            tsg: true,
+            health: true,
+            metrics: true,
+            review: false,
+            compile: false,
+            profiles: false,
+        }
+    }
+}
+
+/// Values the form is pre-filled with.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub department: String,
+    pub monthly_hours: u32,
+    pub hourly_wage: String,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Self {
+            department: "Institut für Informatik".to_string(),
+            monthly_hours: 40,
+            hourly_wage: "14.09".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Render {
+    pub max_renders: usize,
+    pub max_queue: usize,
+    /// In seconds.
+    pub timeout: u64,
+    /// How long running requests may take to finish when shutting down, in
+    /// seconds.
+    pub shutdown_timeout: u64,
+}
+
+impl Default for Render {
+    fn default() -> Self {
+        Self {
+            max_renders: 4,
+            max_queue: 32,
+            timeout: 30,
+            shutdown_timeout: 30,
+        }
+    }
+}
+
+/// Limits for compiling self-written typst files.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sandbox {
+    /// In seconds. Should be shorter than `render.timeout`.
+    pub timeout: u64,
+    /// Address space available to the compiler, in MiB.
+    pub max_memory_mib: u64,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            timeout: 10,
+            max_memory_mib: 1024,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Review {
+    /// SQLite database submitted time sheets are stored in.
+    pub database: PathBuf,
+    /// The URL under which the app is reachable from the outside, including
+    /// the base path. Used for links in notification emails.
+    pub public_url: Option<String>,
+    /// PKCS#12 file approved time sheets are signed with.
+    pub certificate: Option<PathBuf>,
+    pub certificate_password: String,
+    /// Domains notification emails may be sent to, including their
+    /// subdomains. Without any, no notifications are sent, so the server can't
+    /// be used to send emails to arbitrary addresses.
+    pub email_domains: Vec<String>,
+    /// Maximum number of notifications sent to the same address per hour.
+    pub max_emails_per_hour: u32,
+}
+
+impl Default for Review {
+    fn default() -> Self {
+        Self {
+            database: PathBuf::from("reviews.sqlite"),
+            public_url: None,
+            certificate: None,
+            certificate_password: String::new(),
+            email_domains: vec![],
+            max_emails_per_hour: 10,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profiles {
+    /// SQLite database saved profiles are stored in.
+    pub database: PathBuf,
+    /// How many profiles each client may create per hour. Clients whose
+    /// address is unknown share the limit.
+    pub max_created_per_hour: u32,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            database: PathBuf::from("profiles.sqlite"),
+            max_created_per_hour: 100,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Unencrypted, only for local mail servers.
+    None,
+    #[default]
+    StartTls,
+    Tls,
+}
+
+/// Mail server used to send notifications about reviews.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Smtp {
+    pub host: String,
+    /// Defaults to the usual port for the TLS mode.
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender address, e.g. `AbzDokGen <abzdokgen@example.com>`.
+    pub from: String,
+}
+
+#[derive(Clone, Copy, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Logging {
+    /// A filter directive like `info` or `arbeitszeitdokumentationsgenerator=debug`.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Branding {
+    /// Shown in the browser tab.
+    pub title: String,
+    /// Shown at the top of every page.
+    pub heading: String,
+    /// Where the "(source)" link next to the heading points to.
+    pub source_link: String,
+}
+
+impl Default for Branding {
+    fn default() -> Self {
+        Self {
+            title: "AbzDokGen".to_string(),
+            heading: "Arbeitszeitdokumentationsgenerator".to_string(),
+            source_link: "https://github.com/Garmelon/Arbeitszeitdokumentationsgenerator"
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// A TCP address, or `unix:<path>` for a Unix domain socket. May be left
+    /// out when using systemd socket activation.
+    pub addr: Option<String>,
+    /// Path prefix under which all endpoints are served, e.g. `/abzdokgen`.
+    pub base_path: String,
+    /// Honor the `X-Forwarded-Prefix`, `X-Forwarded-Proto` and
+    /// `X-Forwarded-For` headers. Any client can send them, so this is only
+    /// safe if all requests pass through a reverse proxy that sets or removes
+    /// them.
+    pub trust_forwarded_headers: bool,
+    /// German state whose public holidays are used during validation.
+    pub holiday_state: String,
+    /// IANA time zone used for today's date, e.g. when dating signatures.
+    pub time_zone: String,
+    /// Directory with additional templates, one per subdirectory.
+    pub templates_dir: Option<PathBuf>,
+    pub endpoints: Endpoints,
+    pub defaults: Defaults,
+    pub render: Render,
+    pub sandbox: Sandbox,
+    pub limits: Limits,
+    pub logging: Logging,
+    pub branding: Branding,
+    pub review: Review,
+    pub profiles: Profiles,
+    /// Without a mail server, no notifications are sent.
+    pub smtp: Option<Smtp>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            addr: None,
+            base_path: String::new(),
+            trust_forwarded_headers: false,
+            holiday_state: DEFAULT_HOLIDAY_STATE.to_string(),
+            time_zone: DEFAULT_TIME_ZONE.to_string(),
+            templates_dir: None,
+            endpoints: Endpoints::default(),
+            defaults: Defaults::default(),
+            render: Render::default(),
+            sandbox: Sandbox::default(),
+            limits: Limits::default(),
+            logging: Logging::default(),
+            branding: Branding::default(),
+            review: Review::default(),
+            profiles: Profiles::default(),
+            smtp: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("failed to parse config file {}", path.display()))
+    }
+
+    pub fn time_zone(&self) -> TimeZone {
+        // Validated when loading the config
+        TimeZone::get(&self.time_zone).unwrap()
+    }
+
+    /// Whether submitting a time sheet for review can notify people by email.
+    pub fn notifications(&self) -> bool {
+        self.smtp.is_some() && !self.review.email_domains.is_empty()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !is_path_prefix(&self.base_path) {
+            bail!(
+                "invalid base_path {:?}, must start with a single slash, must not end \
+                 with one and may only contain letters, digits and -._~/",
+                self.base_path
+            );
+        }
+
+        if !HOLIDAY_STATES.contains(&self.holiday_state.as_str()) {
+            bail!(
+                "invalid holiday_state {:?}, expected one of {}",
+                self.holiday_state,
+                HOLIDAY_STATES.join(", ")
+            );
+        }
+
+        if let Err(error) = TimeZone::get(&self.time_zone) {
+            bail!("invalid time_zone {:?}: {error}", self.time_zone);
+        }
+
+        if !self.endpoints.index && !self.endpoints.tsg {
+            bail!("at least one of endpoints.index and endpoints.tsg must be enabled");
+        }
+
+        if self.endpoints.review && !self.endpoints.index {
+            bail!("endpoints.review requires endpoints.index");
+        }
+
+        if self.endpoints.profiles && !self.endpoints.index {
+            bail!("endpoints.profiles requires endpoints.index");
+        }
+
+        if let Some(url) = &self.review.public_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") || url.ends_with('/') {
+                bail!(
+                    "invalid review.public_url {url:?}, must be an http(s) URL without a trailing slash"
+                );
+            }
+        }
+
+        if self.endpoints.review && self.notifications() && self.review.public_url.is_none() {
+            bail!("review.public_url is required for sending notifications");
+        }
+
+        let invalid_domain = |domain: &&String| domain.is_empty() || domain.contains('@');
+        if let Some(domain) = self.review.email_domains.iter().find(invalid_domain) {
+            bail!("invalid review.email_domains entry {domain:?}, must be a domain like kit.edu");
+        }
+
+        if self.defaults.hourly_wage.parse::<f64>().is_err() {
+            bail!(
+                "invalid defaults.hourly_wage {:?}",
+                self.defaults.hourly_wage
+            );
+        }
+
+        if self.render.max_renders == 0 {
+            bail!("render.max_renders must be at least 1");
+        }
+
+        if self.render.timeout == 0 {
+            bail!("render.timeout must be at least 1");
+        }
+
+        if self.endpoints.compile
+            && (self.sandbox.timeout == 0 || self.sandbox.timeout >= self.render.timeout)
+        {
+            bail!("sandbox.timeout must be at least 1 and shorter than render.timeout");
+        }
+
+        if self.sandbox.max_memory_mib == 0 {
+            bail!("sandbox.max_memory_mib must be at least 1");
+        }
+
+        if let Err(error) = EnvFilter::try_new(&self.logging.level) {
+            bail!("invalid logging.level {:?}: {error}", self.logging.level);
+        }
+
+        if self.limits.max_body_bytes == 0 {
+            bail!("limits.max_body_bytes must be at least 1");
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a path can be put in front of the app's paths, e.g. `/abzdokgen`.
+/// Apart from the empty prefix, it must start with a single slash so it isn't
+/// mistaken for a host (`//evil.example`), must not end with a slash and may
+/// only contain characters that need no escaping in URLs, headers and cookies.
+pub fn is_path_prefix(path: &str) -> bool {
+    path.is_empty()
+        || (path.starts_with('/')
+            && !path.starts_with("//")
+            && !path.ends_with('/')
+            && (path.chars()).all(|c| c.is_ascii_alphanumeric() || "-._~/".contains(c)))
+}
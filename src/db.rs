@@ -0,0 +1,43 @@
+use std::{
+    panic,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use rusqlite::Connection;
+
+/// A SQLite database shared by all requests. Queries run on the blocking
+/// thread pool, so waiting for the disk or for another query doesn't hold up
+/// the async runtime.
+#[derive(Clone)]
+pub struct Database {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    /// Open the database, creating the tables in `schema` if necessary.
+    pub fn open(path: &Path, schema: &str) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        connection
+            .execute_batch(schema)
+            .with_context(|| format!("failed to set up database {}", path.display()))?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    pub async fn run<T, F>(&self, query: F) -> rusqlite::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        let result = tokio::task::spawn_blocking(move || query(&connection.lock().unwrap())).await;
+        match result {
+            Ok(result) => result,
+            Err(error) => panic::resume_unwind(error.into_panic()),
+        }
+    }
+}
@@ -1,19 +1,411 @@
-use el::{html::*, Document, ElementComponent};
+use std::{
+    convert::Infallible,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use axum::{
+    extract::{ConnectInfo, DefaultBodyLimit, FromRequestParts, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use el::{html::*, Document, Element, ElementComponent};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use self::i18n::{fill, Lang, Messages};
+use crate::{
+    config::{is_path_prefix, Config},
+    limits::LimitError,
+    listen::PeerAddr,
+    metrics::Metrics,
+    pool::{PoolError, RenderPool},
+    render::{self, Format, InputError, SelfTest, Template, Templates, Timesheet, Timings},
+    sandbox::Sandbox,
+    sign::{self, Identity},
+};
+
+pub mod compile;
+pub mod health;
+pub mod i18n;
 pub mod index;
+pub mod metrics;
+pub mod profile;
+pub mod review;
 pub mod tsg;
 
-fn page(head: impl ElementComponent, body: impl ElementComponent) -> Document {
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Arc<RenderPool>,
+    pub metrics: Arc<Metrics>,
+    pub config: Arc<Config>,
+    pub templates: Arc<Templates>,
+    pub sandbox: Arc<Sandbox>,
+    /// Only set once the self test passed.
+    pub self_test: Arc<Mutex<Option<SelfTest>>>,
+    /// Only present if the review endpoints are enabled.
+    pub review: Option<Arc<review::ReviewState>>,
+    /// Only present if profiles are enabled.
+    pub profiles: Option<Arc<profile::ProfileState>>,
+}
+
+impl AppState {
+    /// Set up everything the enabled endpoints need. Watching the templates
+    /// directory for changes and deleting expired profiles is left to the
+    /// caller.
+    pub fn new(config: Config) -> anyhow::Result<Self> {
+        let metrics = Arc::new(Metrics::new()?);
+        let pool = RenderPool::new(
+            config.render.max_renders,
+            config.render.max_queue,
+            Duration::from_secs(config.render.timeout),
+            metrics.clone(),
+        );
+
+        let templates = Arc::new(match &config.templates_dir {
+            Some(dir) => Templates::with_dir(dir.clone())?,
+            None => Templates::builtin(),
+        });
+
+        let review = match config.endpoints.review {
+            true => Some(Arc::new(review::ReviewState::new(&config)?)),
+            false => None,
+        };
+
+        let profiles = match config.endpoints.profiles {
+            true => Some(Arc::new(profile::ProfileState::new(&config)?)),
+            false => None,
+        };
+
+        Ok(Self {
+            pool: Arc::new(pool),
+            metrics,
+            sandbox: Arc::new(Sandbox::new(&config)),
+            config: Arc::new(config),
+            templates,
+            self_test: Arc::new(Mutex::new(None)),
+            review,
+            profiles,
+        })
+    }
+}
+
+/// All enabled endpoints, served under the configured base path.
+pub fn router(state: AppState) -> Router {
+    let config = &state.config;
+
+    let mut app = Router::new();
+    if config.endpoints.index {
+        app = app
+            .route("/", get(index::get).post(index::post))
+            .route("/preview", post(index::preview))
+            .route("/import", post(index::import))
+            .route("/verify", post(index::verify));
+    }
+    if config.endpoints.review {
+        app = app
+            .route("/submit", post(index::submit))
+            .route("/reviews/{id}", get(review::status))
+            .route("/reviews/{id}/pdf", get(review::pdf))
+            .route("/review/{token}", get(review::get).post(review::post));
+    }
+    if config.endpoints.profiles {
+        app = app.route(
+            "/profile",
+            get(profile::get)
+                .post(profile::post)
+                .delete(profile::delete),
+        );
+    }
+    app = app.route("/language", get(i18n::set));
+    if config.endpoints.compile {
+        app = app.route("/compile", post(compile::post));
+    }
+    if config.endpoints.tsg {
+        app = app.route("/tsg/", get(tsg::get).post(tsg::post));
+    }
+    if config.endpoints.health {
+        app = app
+            .route("/healthz", get(health::healthz))
+            .route("/readyz", get(health::readyz));
+    }
+    if config.endpoints.metrics {
+        app = app.route("/metrics", get(metrics::get));
+    }
+
+    let base = &config.base_path;
+    if !base.is_empty() {
+        // Nested under `{base}`, the index page would end up at `{base}`
+        // instead of `{base}/`.
+        app = Router::new()
+            .route(base, get(redirect_to_index))
+            .nest(&format!("{base}/"), app);
+    }
+
+    app.layer(DefaultBodyLimit::max(config.limits.max_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track_requests,
+        ))
+        .with_state(state)
+}
+
+fn page(config: &Config, head: impl ElementComponent, body: impl ElementComponent) -> Document {
     html((
         el::html::head((
             meta((
                 attr::name("viewport"),
                 attr::content("width=device-width, initial-scale=1"),
             )),
-            title("AbzDokGen"),
+            title(&config.branding.title),
             head,
         )),
         el::html::body(body),
     ))
     .into_document()
 }
+
+/// The path under which the app is reachable from the outside, without a
+/// trailing slash. Trusted reverse proxies that strip a prefix before
+/// forwarding requests can announce it via the `X-Forwarded-Prefix` header.
+fn base_path(config: &Config, headers: &HeaderMap) -> String {
+    let forwarded = match config.trust_forwarded_headers {
+        true => headers.get("x-forwarded-prefix"),
+        false => None,
+    };
+    let prefix = forwarded
+        .and_then(|prefix| prefix.to_str().ok())
+        .map(|prefix| prefix.trim_end_matches('/'))
+        .filter(|prefix| is_path_prefix(prefix))
+        .unwrap_or("");
+    format!("{prefix}{}", config.base_path)
+}
+
+/// Whether the app is reachable via HTTPS from the outside. The server itself
+/// only speaks HTTP, so this relies on a trusted reverse proxy's
+/// `X-Forwarded-Proto` header.
+fn is_https(config: &Config, headers: &HeaderMap) -> bool {
+    let forwarded = match config.trust_forwarded_headers {
+        true => headers.get("x-forwarded-proto"),
+        false => None,
+    };
+    forwarded.is_some_and(|proto| proto.as_bytes().eq_ignore_ascii_case(b"https"))
+}
+
+/// The address of the client that sent the request, if known. Behind a trusted
+/// reverse proxy that's the last address in `X-Forwarded-For`, since earlier
+/// ones are sent by the client, otherwise the peer's address.
+pub struct ClientAddr(pub Option<IpAddr>);
+
+impl FromRequestParts<AppState> for ClientAddr {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let forwarded = match state.config.trust_forwarded_headers {
+            true => parts.headers.get_all("x-forwarded-for").iter().next_back(),
+            false => None,
+        };
+        let forwarded = forwarded
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|addr| addr.trim().parse().ok());
+        let peer = || {
+            let ConnectInfo(PeerAddr(addr)) = parts.extensions.get::<ConnectInfo<PeerAddr>>()?;
+            addr.map(|addr| addr.ip())
+        };
+        Ok(Self(forwarded.or_else(peer)))
+    }
+}
+
+/// The value of a cookie sent by the browser.
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    (headers.get_all(header::COOKIE).iter())
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(name)?.strip_prefix('='))
+}
+
+async fn redirect_to_index(state: State<AppState>, headers: HeaderMap) -> Redirect {
+    Redirect::permanent(&format!("{}/", base_path(&state.config, &headers)))
+}
+
+fn heading(config: &Config) -> Element {
+    h1((
+        format!("{} ", config.branding.heading),
+        a((
+            attr::id("source"),
+            attr::href(&config.branding.source_link),
+            "(source)",
+        )),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FormatQuery {
+    format: Option<String>,
+    dpi: Option<f32>,
+    standard: Option<String>,
+    /// Fixed date (`YYYY-MM-DD`) to use as today, for reproducible output.
+    today: Option<String>,
+    template: Option<String>,
+}
+
+impl FormatQuery {
+    fn format(&self) -> Result<Format, InputError> {
+        Format::parse(
+            self.format.as_deref().unwrap_or("pdf"),
+            self.dpi,
+            self.standard.as_deref(),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateQuery {
+    template: Option<String>,
+}
+
+/// Look up the requested template and check that the time sheet fits it.
+fn template(
+    state: &AppState,
+    name: Option<&str>,
+    timesheet: &Timesheet,
+    m: &Messages,
+) -> Result<Arc<dyn Template>, String> {
+    let template = state.templates.get(name, m)?;
+    template.check(timesheet, m)?;
+    Ok(template)
+}
+
+fn error_response<S: ToString>(msg: S) -> Response {
+    (StatusCode::BAD_REQUEST, msg.to_string()).into_response()
+}
+
+fn limit_error_response(error: LimitError) -> Response {
+    match error {
+        LimitError::TooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg).into_response(),
+        LimitError::Invalid(msg) => error_response(msg),
+    }
+}
+
+fn storage_error_response(error: rusqlite::Error, lang: Lang) -> Response {
+    error!(%error, "database query failed");
+    let msg = lang.messages().storage_failed;
+    (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+}
+
+fn pool_error_response(error: PoolError, lang: Lang) -> Response {
+    let messages = lang.messages();
+    let (status, msg) = match error {
+        PoolError::Busy => (StatusCode::SERVICE_UNAVAILABLE, messages.busy),
+        PoolError::Timeout => (StatusCode::SERVICE_UNAVAILABLE, messages.timeout),
+        PoolError::Panicked => (StatusCode::INTERNAL_SERVER_ERROR, messages.panicked),
+    };
+    (status, msg).into_response()
+}
+
+/// Render a timesheet, optionally signing the resulting PDF.
+async fn render_response(
+    state: &AppState,
+    endpoint: &str,
+    timesheet: Timesheet,
+    query: &FormatQuery,
+    identity: Option<Identity>,
+    lang: Lang,
+) -> Response {
+    let m = lang.messages();
+    let format = match query.format() {
+        Ok(format) => format,
+        Err(error) => {
+            info!(category = "input", "invalid format");
+            state.metrics.count_render(endpoint, "input");
+            return error_response(error.message(m));
+        }
+    };
+
+    if identity.is_some() && !matches!(format, Format::Pdf { .. }) {
+        info!(category = "input", "signing non-pdf format");
+        state.metrics.count_render(endpoint, "input");
+        return error_response(m.sign_non_pdf);
+    }
+
+    let now = match render::now(state.config.time_zone(), query.today.as_deref()) {
+        Ok(now) => now,
+        Err(error) => {
+            info!(category = "input", "invalid date");
+            state.metrics.count_render(endpoint, "input");
+            return error_response(error.message(m));
+        }
+    };
+
+    if let Err(error) = state.config.limits.check(&timesheet, m) {
+        info!(category = error.category(), "input exceeds limits");
+        state.metrics.count_render(endpoint, error.category());
+        return limit_error_response(error);
+    }
+
+    let template = match template(state, query.template.as_deref(), &timesheet, m) {
+        Ok(template) => template,
+        Err(error) => {
+            info!(category = "input", "invalid template");
+            state.metrics.count_render(endpoint, "input");
+            return error_response(error);
+        }
+    };
+
+    // Never log anything from the time sheet itself, it contains personal data.
+    let entries = timesheet.entries.len();
+    let start = Instant::now();
+    let result = state
+        .pool
+        .run(move || {
+            let mut timings = Timings::default();
+            let result = render::render_timed(&*template, timesheet, format, now, &mut timings);
+            let result = match (result, identity) {
+                (Ok(pdf), Some(identity)) => sign::sign_pdf(&pdf, &identity)
+                    .map_err(|e| vec![fill(m.signing_failed, &[("error", &e)])]),
+                (result, _) => result,
+            };
+            (result, timings)
+        })
+        .await;
+    let duration = start.elapsed();
+
+    match result {
+        Ok((Ok(data), timings)) => {
+            // Failed renders stop before exporting, so their timings are incomplete.
+            state.metrics.observe_render(format.name(), &timings);
+            state.metrics.count_render(endpoint, "success");
+            info!(
+                entries,
+                ?duration,
+                format = format.name(),
+                "rendered document"
+            );
+            ([(header::CONTENT_TYPE, format.content_type())], data).into_response()
+        }
+        Ok((Err(errors), _)) => {
+            info!(entries, ?duration, category = "typst", "rendering failed");
+            state.metrics.count_render(endpoint, "typst");
+            error_response(errors.join("\n"))
+        }
+        Err(error) => {
+            warn!(
+                entries,
+                ?duration,
+                category = error.category(),
+                "rendering failed"
+            );
+            state.metrics.count_render(endpoint, error.category());
+            pool_error_response(error, lang)
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Instant;
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    endpoints::{error_response, i18n::Lang, pool_error_response, AppState, TemplateQuery},
+    render::{Compiled, Diagnostic},
+    sandbox::SandboxError,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct DiagnosticJson {
+    /// `error` or `warning`.
+    severity: String,
+    message: String,
+    hints: Vec<String>,
+    /// Template file the diagnostic refers to, missing for the uploaded file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+impl From<Diagnostic> for DiagnosticJson {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self {
+            severity: match diagnostic.error {
+                true => "error".to_string(),
+                false => "warning".to_string(),
+            },
+            message: diagnostic.message,
+            hints: diagnostic.hints,
+            file: diagnostic.file,
+            line: diagnostic.line,
+            column: diagnostic.column,
+        }
+    }
+}
+
+/// Result of compiling a self-written file, also passed from the sandboxed
+/// child process to the server.
+#[derive(Serialize, Deserialize)]
+pub struct CompileJson {
+    /// Base64-encoded, missing if compiling failed.
+    pdf: Option<String>,
+    diagnostics: Vec<DiagnosticJson>,
+}
+
+impl From<Compiled> for CompileJson {
+    fn from(compiled: Compiled) -> Self {
+        Self {
+            pdf: compiled.pdf.map(|pdf| BASE64.encode(pdf)),
+            diagnostics: compiled.diagnostics.into_iter().map(|d| d.into()).collect(),
+        }
+    }
+}
+
+fn sandbox_error_response(error: SandboxError, lang: Lang) -> Response {
+    let messages = lang.messages();
+    let (status, msg) = match error {
+        SandboxError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, messages.sandbox_failed),
+        SandboxError::Timeout => (StatusCode::UNPROCESSABLE_ENTITY, messages.sandbox_timeout),
+        SandboxError::Aborted => (StatusCode::UNPROCESSABLE_ENTITY, messages.sandbox_memory),
+    };
+    (status, msg).into_response()
+}
+
+/// Compile a self-written main file sent as the request body. It can import
+/// the files of the selected template, but nothing else.
+pub async fn post(
+    state: State<AppState>,
+    Query(query): Query<TemplateQuery>,
+    lang: Lang,
+    source: Bytes,
+) -> Response {
+    let endpoint = "compile";
+    let m = lang.messages();
+
+    let template = match state.templates.get(query.template.as_deref(), m) {
+        Ok(template) => template.name().to_string(),
+        Err(error) => {
+            info!(category = "input", "invalid template");
+            state.metrics.count_render(endpoint, "input");
+            return error_response(error);
+        }
+    };
+
+    if std::str::from_utf8(&source).is_err() {
+        info!(category = "input", "source is not valid UTF-8");
+        state.metrics.count_render(endpoint, "input");
+        return error_response(m.source_not_utf8);
+    }
+
+    // Never log the source itself, it may contain personal data.
+    let bytes = source.len();
+    let start = Instant::now();
+    let sandbox = state.sandbox.clone();
+    let result = state
+        .pool
+        .run(move || sandbox.compile(&template, &source))
+        .await;
+    let duration = start.elapsed();
+
+    let output = match result {
+        Ok(Ok(output)) => output,
+        Ok(Err(error)) => {
+            let category = error.category();
+            match &error {
+                SandboxError::Io(error) => {
+                    warn!(bytes, ?duration, category, %error, "compiling failed")
+                }
+                _ => info!(bytes, ?duration, category, "compiling failed"),
+            }
+            state.metrics.count_render(endpoint, category);
+            return sandbox_error_response(error, lang);
+        }
+        Err(error) => {
+            warn!(
+                bytes,
+                ?duration,
+                category = error.category(),
+                "compiling failed"
+            );
+            state.metrics.count_render(endpoint, error.category());
+            return pool_error_response(error, lang);
+        }
+    };
+
+    let compiled = match serde_json::from_slice::<CompileJson>(&output) {
+        Ok(compiled) => compiled,
+        Err(error) => {
+            warn!(bytes, ?duration, category = "sandbox", %error, "invalid sandbox output");
+            state.metrics.count_render(endpoint, "sandbox");
+            return sandbox_error_response(SandboxError::Io(error.into()), lang);
+        }
+    };
+
+    if compiled.pdf.is_some() {
+        info!(bytes, ?duration, "compiled document");
+        state.metrics.count_render(endpoint, "success");
+        Json(compiled).into_response()
+    } else {
+        info!(bytes, ?duration, category = "typst", "compiling failed");
+        state.metrics.count_render(endpoint, "typst");
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(compiled)).into_response()
+    }
+}
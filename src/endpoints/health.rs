@@ -0,0 +1,93 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    endpoints::AppState,
+    render::{self, SelfTest},
+};
+
+#[derive(Serialize)]
+pub struct FontJson {
+    family: String,
+    found: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReadyJson {
+    ready: bool,
+    version: &'static str,
+    template_version: String,
+    fonts: Vec<FontJson>,
+    problems: Vec<String>,
+}
+
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+async fn run_self_test(state: &AppState) -> SelfTest {
+    let template = state.templates.default();
+    let self_test = tokio::task::spawn_blocking(move || render::self_test(&*template))
+        .await
+        .unwrap_or_else(|_| SelfTest {
+            template_version: String::new(),
+            fonts: vec![],
+            problems: vec!["self test panicked".to_string()],
+        });
+    if !self_test.ok() {
+        let missing_fonts = (self_test.fonts.iter())
+            .filter(|font| !font.found)
+            .map(|font| font.family.as_str())
+            .collect::<Vec<_>>();
+        warn!(
+            ?missing_fonts,
+            problems = ?self_test.problems,
+            "self test failed"
+        );
+    }
+    self_test
+}
+
+fn ready_response(self_test: &SelfTest) -> Response {
+    let ready = self_test.ok();
+    let status = match ready {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    let json = ReadyJson {
+        ready,
+        version: env!("CARGO_PKG_VERSION"),
+        template_version: self_test.template_version.clone(),
+        fonts: (self_test.fonts.iter())
+            .map(|font| FontJson {
+                family: font.family.clone(),
+                found: font.found,
+            })
+            .collect(),
+        problems: self_test.problems.clone(),
+    };
+    (status, Json(json)).into_response()
+}
+
+pub async fn readyz(state: State<AppState>) -> Response {
+    // A passed self test is cached, it doesn't need to occupy a render slot
+    // every time a load balancer asks. A failed one is run again on the next
+    // request, since e.g. installing missing fonts fixes it without a restart.
+    // Holding the lock also keeps concurrent requests from running it twice.
+    let mut cached = state.self_test.lock().await;
+    if cached.is_none() {
+        let self_test = run_self_test(&state).await;
+        if !self_test.ok() {
+            return ready_response(&self_test);
+        }
+        *cached = Some(self_test);
+    }
+    // Set above if it wasn't already.
+    ready_response(cached.as_ref().unwrap())
+}
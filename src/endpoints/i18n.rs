@@ -0,0 +1,430 @@
+use std::{convert::Infallible, fmt::Display};
+
+use axum::{
+    extract::{FromRequestParts, Query, State},
+    http::{header, request::Parts, HeaderMap},
+    response::{IntoResponse, Redirect, Response},
+};
+use el::{html::*, Element};
+use serde::{Deserialize, Serialize};
+
+use crate::endpoints::{base_path, cookie, AppState};
+
+mod de;
+mod en;
+
+/// How long the language chosen via the toggle is remembered, in seconds.
+const COOKIE_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// Language of the web UI. Documents have their own language, see
+/// [`crate::render::Language`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    De,
+    En,
+}
+
+impl Lang {
+    pub const ALL: [Self; 2] = [Self::De, Self::En];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::De => "de",
+            Self::En => "en",
+        }
+    }
+
+    fn parse(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    pub fn messages(self) -> &'static Messages {
+        match self {
+            Self::De => &de::MESSAGES,
+            Self::En => &en::MESSAGES,
+        }
+    }
+
+    /// The supported language the browser prefers most, e.g. `de` for
+    /// `en-US;q=0.5, de-DE`.
+    fn from_accept_language(value: &str) -> Option<Self> {
+        let mut best = None::<(f32, Self)>;
+        for item in value.split(',') {
+            let mut parts = item.split(';');
+            let tag = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let quality = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let Some(lang) = Self::parse(tag.split('-').next().unwrap_or("")) else {
+                continue;
+            };
+            let better = match best {
+                Some((best_quality, _)) => quality > best_quality,
+                None => quality > 0.0,
+            };
+            if better {
+                best = Some((quality, lang));
+            }
+        }
+        best.map(|(_, lang)| lang)
+    }
+
+    /// The language chosen via the toggle, or else the one preferred by the
+    /// browser. Defaults to German.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let chosen = cookie(headers, "lang").and_then(Self::parse);
+        let preferred = || {
+            (headers.get(header::ACCEPT_LANGUAGE))
+                .and_then(|value| value.to_str().ok())
+                .and_then(Self::from_accept_language)
+        };
+        chosen.or_else(preferred).unwrap_or(Self::De)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Lang {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// Replace `{name}` placeholders in a message.
+pub fn fill(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut result = message.to_string();
+    for (name, value) in args {
+        result = result.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    result
+}
+
+/// Texts shown by the scripts, passed to them as `window.messages`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptMessages {
+    pub generating: &'static str,
+    pub generate_failed: &'static str,
+    pub generate_succeeded: &'static str,
+    /// Name of the downloaded PDF.
+    pub file_name: &'static str,
+    /// Contains a `{file}` placeholder.
+    pub read_failed: &'static str,
+    pub submitting: &'static str,
+    pub submit_failed: &'static str,
+    pub submit_succeeded: &'static str,
+    pub status_link: &'static str,
+    pub review_link: &'static str,
+    pub review_link_hint: &'static str,
+    pub preview_failed: &'static str,
+    pub importing: &'static str,
+    pub import_failed: &'static str,
+    /// Contains `{entries}` and `{max}` placeholders.
+    pub import_too_many_entries: &'static str,
+    pub import_succeeded: &'static str,
+    pub saving_profile: &'static str,
+    pub save_profile_failed: &'static str,
+    pub save_profile_succeeded: &'static str,
+    pub delete_profile_failed: &'static str,
+    pub delete_profile_succeeded: &'static str,
+}
+
+/// A translation catalog. Being a struct, every catalog is guaranteed to
+/// contain every message.
+#[derive(Serialize)]
+pub struct Messages {
+    /// Link text of the toggle switching to this language.
+    pub language_name: &'static str,
+
+    // Index page
+    pub intro_before: &'static str,
+    pub intro_json: &'static str,
+    pub intro_tsg: &'static str,
+    pub intro_template_before: &'static str,
+    pub intro_template: &'static str,
+    pub intro_after: &'static str,
+    pub import_title: &'static str,
+    pub import_label: &'static str,
+    pub template_label: &'static str,
+    pub document_language_label: &'static str,
+    pub document_language_de: &'static str,
+    pub document_language_en: &'static str,
+    pub document_language_de_en: &'static str,
+    pub month_label: &'static str,
+    pub name_label: &'static str,
+    pub staff_id_label: &'static str,
+    pub gf_title: &'static str,
+    pub ub_title: &'static str,
+    pub department_title: &'static str,
+    pub department_label: &'static str,
+    pub monthly_hours_label: &'static str,
+    pub hours_unit: &'static str,
+    pub hourly_wage_label: &'static str,
+    pub carry_label: &'static str,
+    pub sort_title: &'static str,
+    pub sort_label: &'static str,
+    pub validate_title: &'static str,
+    pub validate_label: &'static str,
+    pub date_signature_title: &'static str,
+    pub date_signature_label: &'static str,
+    pub signature_date_title: &'static str,
+    pub signature_date_label: &'static str,
+    pub signature_title: &'static str,
+    pub signature_label: &'static str,
+    pub certificate_title: &'static str,
+    pub certificate_label: &'static str,
+    pub certificate_password_label: &'static str,
+    pub task_header: &'static str,
+    pub task_header_hint: &'static str,
+    pub day_header: &'static str,
+    pub start_header: &'static str,
+    pub end_header: &'static str,
+    pub rest_header: &'static str,
+    pub duration_header: &'static str,
+    pub note_normal: &'static str,
+    pub note_urlaub: &'static str,
+    pub note_krankheit: &'static str,
+    pub note_feiertag: &'static str,
+    pub note_sonstiges: &'static str,
+    pub add_row_button: &'static str,
+    pub remove_row_button: &'static str,
+    pub generate_button: &'static str,
+    pub employee_email_label: &'static str,
+    pub supervisor_email_label: &'static str,
+    pub submit_review_title: &'static str,
+    pub submit_review_button: &'static str,
+    pub preview_alt: &'static str,
+    pub profile_hint: &'static str,
+    pub save_profile_button: &'static str,
+    pub export_profile_link: &'static str,
+    pub delete_profile_button: &'static str,
+
+    // TSG page
+    pub tsg_intro_before: &'static str,
+    pub tsg_intro_form: &'static str,
+    pub tsg_intro_after: &'static str,
+
+    // Review pages
+    /// `{month}`, `{name}`
+    pub review_title: &'static str,
+    pub review_pending: &'static str,
+    /// `{name}`
+    pub review_approved: &'static str,
+    pub review_rejected: &'static str,
+    pub download_submitted_pdf: &'static str,
+    pub download_approved_pdf: &'static str,
+    pub approver_name_label: &'static str,
+    pub approve_button: &'static str,
+    pub comment_label: &'static str,
+    pub reject_button: &'static str,
+
+    // Notifications
+    /// `{month}`
+    pub submitted_subject: &'static str,
+    /// `{title}`
+    pub submitted_text: &'static str,
+    /// `{month}`
+    pub approved_subject: &'static str,
+    /// `{title}`, `{name}`
+    pub approved_text: &'static str,
+    /// `{month}`
+    pub rejected_subject: &'static str,
+    /// `{title}`, `{comment}`
+    pub rejected_text: &'static str,
+
+    // Field names in limit errors
+    pub field_name: &'static str,
+    pub field_staff_id: &'static str,
+    pub field_department: &'static str,
+    pub field_hourly_wage: &'static str,
+    pub field_carry: &'static str,
+    pub field_approver_name: &'static str,
+    /// `{field}`, `{row}`
+    pub field_in_row: &'static str,
+
+    // Errors, placeholders are noted in brackets
+    /// `{value}`
+    pub invalid_working_area: &'static str,
+    /// `{value}`
+    pub invalid_working_time: &'static str,
+    /// `{value}`
+    pub invalid_month: &'static str,
+    /// `{value}`
+    pub invalid_note: &'static str,
+    /// `{row}`, `{fields}`
+    pub incomplete_row: &'static str,
+    /// `{value}`
+    pub invalid_signature_date: &'static str,
+    /// `{value}`
+    pub invalid_email: &'static str,
+    /// `{domain}`
+    pub email_domain_not_allowed: &'static str,
+    pub invalid_certificate_file: &'static str,
+    pub invalid_certificate: &'static str,
+    pub no_private_key: &'static str,
+    pub unsupported_key: &'static str,
+    pub no_certificate: &'static str,
+    /// `{error}`
+    pub invalid_signature: &'static str,
+    pub sign_non_pdf: &'static str,
+    pub busy: &'static str,
+    pub timeout: &'static str,
+    pub panicked: &'static str,
+    pub profile_not_found: &'static str,
+    pub too_many_profiles: &'static str,
+    /// `{entries}`, `{max}`
+    pub too_many_entries: &'static str,
+    /// `{template}`, `{entries}`, `{max}`
+    pub too_many_entries_for_template: &'static str,
+    /// `{field}`, `{chars}`, `{max}`
+    pub field_too_long: &'static str,
+    /// `{field}`, `{char}`
+    pub invalid_character: &'static str,
+    /// `{value}`
+    pub unknown_template: &'static str,
+    pub invalid_signature_image: &'static str,
+    /// `{value}`
+    pub unsupported_signature_image: &'static str,
+    /// `{value}`
+    pub unknown_language: &'static str,
+    /// `{value}`
+    pub invalid_standard: &'static str,
+    /// `{value}`
+    pub standard_not_supported: &'static str,
+    /// `{value}`
+    pub invalid_format: &'static str,
+    /// `{value}`, `{max}`
+    pub invalid_dpi: &'static str,
+    /// `{value}`
+    pub invalid_date: &'static str,
+    /// `{error}`
+    pub invalid_pdf: &'static str,
+    pub no_timesheet_data: &'static str,
+    /// `{error}`
+    pub invalid_timesheet_data: &'static str,
+    /// `{error}`
+    pub signing_failed: &'static str,
+    pub storage_failed: &'static str,
+    pub source_not_utf8: &'static str,
+    pub sandbox_failed: &'static str,
+    pub sandbox_timeout: &'static str,
+    pub sandbox_memory: &'static str,
+    pub review_not_found: &'static str,
+    pub already_reviewed: &'static str,
+    pub approver_name_missing: &'static str,
+    /// `{value}`
+    pub invalid_approval_date: &'static str,
+    pub comment_missing: &'static str,
+    /// `{value}`
+    pub invalid_decision: &'static str,
+
+    pub script: ScriptMessages,
+}
+
+/// Links switching to the other languages.
+pub fn toggle(base: &str, lang: Lang, page: &str) -> Element {
+    p((
+        attr::id("language"),
+        (Lang::ALL.into_iter())
+            .filter(|other| *other != lang)
+            .map(|other| {
+                a((
+                    attr::href(format!("{base}/language?lang={}&page={page}", other.code())),
+                    other.messages().language_name,
+                ))
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Provides the messages to the page's scripts. Must come before them.
+pub fn script_messages(lang: Lang) -> Element {
+    // Serializing plain data to JSON can't fail.
+    let json = serde_json::to_string(&lang.messages().script).unwrap();
+    script(format!("window.messages = {};", json.replace("</", "<\\/")))
+}
+
+#[derive(Deserialize)]
+pub struct LanguageQuery {
+    lang: String,
+    /// The page to return to, `index` or `tsg`.
+    page: Option<String>,
+}
+
+/// Remember the chosen language in a cookie and return to the page.
+pub async fn set(
+    state: State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<LanguageQuery>,
+) -> Response {
+    let base = base_path(&state.config, &headers);
+    let back = match query.page.as_deref() {
+        Some("tsg") => format!("{base}/tsg/"),
+        _ => format!("{base}/"),
+    };
+    let Some(lang) = Lang::parse(&query.lang) else {
+        return Redirect::to(&back).into_response();
+    };
+
+    let cookie = format!(
+        "lang={}; Path={base}/; Max-Age={COOKIE_MAX_AGE}; SameSite=Lax",
+        lang.code()
+    );
+    ([(header::SET_COOKIE, cookie)], Redirect::to(&back)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use serde_json::Value;
+
+    use super::Lang;
+
+    /// Every message of a catalog by name, including the script messages.
+    fn messages(lang: Lang) -> BTreeMap<String, String> {
+        fn collect(prefix: &str, value: Value, out: &mut BTreeMap<String, String>) {
+            match value {
+                Value::Object(fields) => {
+                    for (name, value) in fields {
+                        collect(&format!("{prefix}{name}."), value, out);
+                    }
+                }
+                Value::String(message) => {
+                    out.insert(prefix.trim_end_matches('.').to_string(), message);
+                }
+                value => panic!("unexpected message {prefix}: {value}"),
+            }
+        }
+
+        let mut out = BTreeMap::new();
+        collect("", serde_json::to_value(lang.messages()).unwrap(), &mut out);
+        out
+    }
+
+    /// The `{name}` placeholders in a message.
+    fn placeholders(message: &str) -> BTreeSet<&str> {
+        (message.split('{').skip(1))
+            .filter_map(|rest| rest.split_once('}'))
+            .map(|(name, _)| name)
+            .filter(|name| name.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+            .collect()
+    }
+
+    #[test]
+    fn catalogs_match() {
+        let de = messages(Lang::De);
+        for lang in Lang::ALL {
+            for (name, message) in messages(lang) {
+                let code = lang.code();
+                assert!(!message.trim().is_empty(), "{code}: {name} is empty");
+                assert_eq!(
+                    placeholders(&message),
+                    placeholders(&de[&name]),
+                    "{code}: {name} has different placeholders than de"
+                );
+            }
+        }
+    }
+}
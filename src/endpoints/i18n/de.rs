@@ -0,0 +1,182 @@
+use super::{Messages, ScriptMessages};
+
+pub const MESSAGES: Messages = Messages {
+    language_name: "Deutsch",
+
+    intro_before: "Du kannst auch ",
+    intro_json: "JSON eingeben",
+    intro_tsg: ", das kompatibel mit dem ",
+    intro_template_before: " ist, oder das dem Generator zugrunde liegende ",
+    intro_template: "Typst-Template",
+    intro_after: " direkt benutzen.",
+    import_title: "Lädt die Daten aus einer hier generierten PDF-Datei.",
+    import_label: "PDF importieren: ",
+    template_label: "Vorlage: ",
+    document_language_label: "Sprache des Dokuments: ",
+    document_language_de: "Deutsch",
+    document_language_en: "Englisch",
+    document_language_de_en: "Deutsch und Englisch",
+    month_label: "Monat / Jahr:",
+    name_label: "Name, Vorname des/r Beschäftigten:",
+    staff_id_label: "Personalnummer:",
+    gf_title: "Großforschung",
+    ub_title: "Unibereich",
+    department_title: "Institut/Organisationseinheit",
+    department_label: "OE:",
+    monthly_hours_label: "Vertraglich vereinbarte Arbeitszeit:",
+    hours_unit: " Std.",
+    hourly_wage_label: "Stundensatz: ",
+    carry_label: "Übertrag vom Vormonat: ",
+    sort_title: "Die Tabelleneinträge werden chronologisch sortiert, \
+                 anstatt dass ihre Reihenfolge beibehalten wird.",
+    sort_label: "Einträge sortieren ",
+    validate_title: "Die Tabelleneinträge werden auf Konsistenz und Korrektheit überprüft, \
+                     bevor das Dokument generiert wird.",
+    validate_label: "Einträge validieren ",
+    date_signature_title: "Das heutige Datum wird neben der Unterschrift eingetragen.",
+    date_signature_label: "Unterschrift datieren ",
+    signature_date_title: "Überschreibt das heutige Datum.",
+    signature_date_label: "Datum: ",
+    signature_title: "Wird über der Unterschriftenlinie eingefügt.",
+    signature_label: "Unterschrift (PNG/SVG): ",
+    certificate_title: "Signiert die PDF-Datei digital. Das Zertifikat wird nur dafür \
+                        verwendet und nicht gespeichert.",
+    certificate_label: "Zertifikat (PKCS#12): ",
+    certificate_password_label: "Passwort: ",
+    task_header: "Tätigkeit",
+    task_header_hint: "(Stichwort, Projekt)",
+    day_header: "Tag",
+    start_header: "Beginn",
+    end_header: "Ende",
+    rest_header: "Pause",
+    duration_header: "Arbeitszeit",
+    note_normal: "Normal",
+    note_urlaub: "Urlaub",
+    note_krankheit: "Krankheit",
+    note_feiertag: "Feiertag",
+    note_sonstiges: "Sonstiges",
+    add_row_button: "Zeile hinzufügen",
+    remove_row_button: "Letzte Zeile entfernen",
+    generate_button: "Arbeitszeitdokumentation generieren",
+    employee_email_label: "Deine E-Mail-Adresse: ",
+    supervisor_email_label: "E-Mail-Adresse des/r Dienstvorgesetzten: ",
+    submit_review_title: "Speichert die Arbeitszeitdokumentation und erzeugt einen Link, \
+                          unter dem sie geprüft und gegengezeichnet werden kann.",
+    submit_review_button: "Zur Prüfung einreichen",
+    preview_alt: "Vorschau",
+    profile_hint: "Name, Personalnummer, OE, Bereich, Arbeitszeit und Stundensatz können \
+                   auf dem Server gespeichert werden, um das Formular beim nächsten Mal \
+                   vorauszufüllen. ",
+    save_profile_button: "Speichern",
+    export_profile_link: "Gespeicherte Daten herunterladen",
+    delete_profile_button: "Gespeicherte Daten löschen",
+
+    tsg_intro_before: "Du kannst deine Daten auch in einem ",
+    tsg_intro_form: "coolen Formular",
+    tsg_intro_after: " eingeben.",
+
+    review_title: "Arbeitszeitdokumentation {month} von {name}",
+    review_pending: "Die Arbeitszeitdokumentation wartet auf ihre Prüfung.",
+    review_approved: "Die Arbeitszeitdokumentation wurde von {name} geprüft und genehmigt.",
+    review_rejected: "Die Arbeitszeitdokumentation wurde abgelehnt. Begründung:",
+    download_submitted_pdf: "Eingereichte PDF-Datei herunterladen",
+    download_approved_pdf: "Geprüfte PDF-Datei herunterladen",
+    approver_name_label: "Name: ",
+    approve_button: "Genehmigen",
+    comment_label: "Begründung: ",
+    reject_button: "Ablehnen",
+
+    submitted_subject: "Arbeitszeitdokumentation {month} zur Prüfung",
+    submitted_text: "{title} wurde zur Prüfung eingereicht.",
+    approved_subject: "Arbeitszeitdokumentation {month} genehmigt",
+    approved_text: "{title} wurde von {name} geprüft und genehmigt.",
+    rejected_subject: "Arbeitszeitdokumentation {month} abgelehnt",
+    rejected_text: "{title} wurde abgelehnt. Begründung:\n\n{comment}",
+
+    field_name: "Name",
+    field_staff_id: "Personalnummer",
+    field_department: "OE",
+    field_hourly_wage: "Stundensatz",
+    field_carry: "Übertrag",
+    field_approver_name: "Name der/des Prüfenden",
+    field_in_row: "{field} in Zeile {row}",
+
+    invalid_working_area: "Ungültiger Bereich: {value}",
+    invalid_working_time: "Ungültige Arbeitszeit: {value}",
+    invalid_month: "Ungültiger Monat: {value}",
+    invalid_note: "Ungültige Bemerkung: {value}",
+    incomplete_row: "Zeile {row} ist unvollständig, es fehlt: {fields}",
+    invalid_signature_date: "Ungültiges Datum der Unterschrift: {value}",
+    invalid_email: "Ungültige E-Mail-Adresse: {value}",
+    email_domain_not_allowed: "E-Mails an Adressen bei {domain} werden nicht verschickt",
+    invalid_certificate_file: "Ungültige Zertifikatsdatei",
+    invalid_certificate: "Ungültige Zertifikatsdatei oder falsches Passwort",
+    no_private_key: "Die Zertifikatsdatei enthält keinen privaten Schlüssel",
+    unsupported_key: "Es werden nur RSA-Schlüssel unterstützt",
+    no_certificate: "Die Zertifikatsdatei enthält kein Zertifikat",
+    invalid_signature: "Ungültige Signatur: {error}",
+    sign_non_pdf: "Nur PDF-Dateien können signiert werden",
+    busy: "Es werden gerade zu viele Dokumente generiert, bitte versuche es später erneut",
+    timeout: "Das Generieren des Dokuments hat zu lange gedauert",
+    panicked: "Beim Generieren des Dokuments ist ein unerwarteter Fehler aufgetreten",
+    profile_not_found: "Es sind keine Daten gespeichert",
+    too_many_profiles: "Es wurden gerade zu viele Profile angelegt, \
+                        bitte versuche es später erneut",
+    too_many_entries: "Zu viele Einträge: {entries} (höchstens {max} erlaubt)",
+    too_many_entries_for_template: "Zu viele Einträge für die Vorlage {template}: {entries} \
+                                    (höchstens {max} passen)",
+    field_too_long: "{field} ist zu lang: {chars} Zeichen (höchstens {max} erlaubt)",
+    invalid_character: "{field} enthält das ungültige Zeichen {char}",
+    unknown_template: "Unbekannte Vorlage: {value}",
+    invalid_signature_image: "Ungültiges Bild der Unterschrift, erlaubt sind PNG und SVG",
+    unsupported_signature_image: "Nicht unterstütztes Bildformat der Unterschrift: {value}, \
+                                  erlaubt sind PNG und SVG",
+    unknown_language: "Unbekannte Sprache: {value}",
+    invalid_standard: "Ungültiger Standard: {value}",
+    standard_not_supported: "Für das Format {value} wird kein Standard unterstützt",
+    invalid_format: "Ungültiges Format: {value}",
+    invalid_dpi: "Ungültige Auflösung: {value} dpi (höchstens {max} erlaubt)",
+    invalid_date: "Ungültiges Datum: {value}",
+    invalid_pdf: "Ungültige PDF-Datei: {error}",
+    no_timesheet_data: "Die PDF-Datei enthält keine Daten einer Arbeitszeitdokumentation",
+    invalid_timesheet_data: "Ungültige Daten in der PDF-Datei: {error}",
+    signing_failed: "Signieren fehlgeschlagen: {error}",
+    storage_failed: "Beim Zugriff auf die Datenbank ist ein Fehler aufgetreten",
+    source_not_utf8: "Die Datei ist nicht in UTF-8 kodiert",
+    sandbox_failed: "Beim Kompilieren des Dokuments ist ein unerwarteter Fehler aufgetreten",
+    sandbox_timeout: "Das Kompilieren des Dokuments hat zu lange gedauert",
+    sandbox_memory: "Das Kompilieren des Dokuments wurde abgebrochen, \
+                     vermutlich weil es zu viel Speicher gebraucht hat",
+    review_not_found: "Die Arbeitszeitdokumentation wurde nicht gefunden",
+    already_reviewed: "Die Arbeitszeitdokumentation wurde bereits geprüft",
+    approver_name_missing: "Der Name fehlt",
+    invalid_approval_date: "Ungültiges Datum: {value}",
+    comment_missing: "Die Begründung fehlt",
+    invalid_decision: "Ungültige Entscheidung: {value}",
+
+    script: ScriptMessages {
+        generating: "Generiere...",
+        generate_failed: "Generieren fehlgeschlagen:",
+        generate_succeeded: "Generieren erfolgreich!",
+        file_name: "Arbeitszeitdokumentation.pdf",
+        read_failed: "{file} konnte nicht gelesen werden:",
+        submitting: "Reiche ein...",
+        submit_failed: "Einreichen fehlgeschlagen:",
+        submit_succeeded: "Einreichen erfolgreich!",
+        status_link: "Status",
+        review_link: "Link für die Prüfung",
+        review_link_hint: "Den Link für die Prüfung bitte nur an die/den \
+                           Dienstvorgesetzte/n weitergeben.",
+        preview_failed: "Vorschau fehlgeschlagen:",
+        importing: "Importiere...",
+        import_failed: "Importieren fehlgeschlagen:",
+        import_too_many_entries: "Die Datei enthält {entries} Einträge, in die gewählte \
+                                  Vorlage passen aber nur {max}.",
+        import_succeeded: "Importieren erfolgreich!",
+        saving_profile: "Speichere...",
+        save_profile_failed: "Speichern fehlgeschlagen:",
+        save_profile_succeeded: "Speichern erfolgreich!",
+        delete_profile_failed: "Löschen fehlgeschlagen:",
+        delete_profile_succeeded: "Gespeicherte Daten gelöscht!",
+    },
+};
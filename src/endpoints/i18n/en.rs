@@ -0,0 +1,177 @@
+use super::{Messages, ScriptMessages};
+
+pub const MESSAGES: Messages = Messages {
+    language_name: "English",
+
+    intro_before: "You can also ",
+    intro_json: "enter JSON",
+    intro_tsg: " compatible with the ",
+    intro_template_before: ", or use the ",
+    intro_template: "typst template",
+    intro_after: " behind this generator directly.",
+    import_title: "Loads the data from a PDF file generated here.",
+    import_label: "Import PDF: ",
+    template_label: "Template: ",
+    document_language_label: "Document language: ",
+    document_language_de: "German",
+    document_language_en: "English",
+    document_language_de_en: "German and English",
+    month_label: "Month / Year:",
+    name_label: "Employee's name (last name, first name):",
+    staff_id_label: "Staff ID:",
+    gf_title: "Großforschung (large-scale research)",
+    ub_title: "Unibereich (university)",
+    department_title: "Institute/organizational unit",
+    department_label: "Unit:",
+    monthly_hours_label: "Contractual working time:",
+    hours_unit: " h",
+    hourly_wage_label: "Hourly wage: ",
+    carry_label: "Carried over from last month: ",
+    sort_title: "The entries are sorted chronologically instead of keeping their order.",
+    sort_label: "Sort entries ",
+    validate_title: "The entries are checked for consistency and correctness \
+                     before the document is generated.",
+    validate_label: "Validate entries ",
+    date_signature_title: "Today's date is entered next to the signature.",
+    date_signature_label: "Date signature ",
+    signature_date_title: "Overrides today's date.",
+    signature_date_label: "Date: ",
+    signature_title: "Placed above the signature line.",
+    signature_label: "Signature (PNG/SVG): ",
+    certificate_title: "Digitally signs the PDF file. The certificate is only used for this \
+                        and isn't stored.",
+    certificate_label: "Certificate (PKCS#12): ",
+    certificate_password_label: "Password: ",
+    task_header: "Task",
+    task_header_hint: "(keyword, project)",
+    day_header: "Day",
+    start_header: "Start",
+    end_header: "End",
+    rest_header: "Break",
+    duration_header: "Working time",
+    note_normal: "Normal",
+    note_urlaub: "Vacation",
+    note_krankheit: "Sickness",
+    note_feiertag: "Public holiday",
+    note_sonstiges: "Other",
+    add_row_button: "Add row",
+    remove_row_button: "Remove last row",
+    generate_button: "Generate time sheet",
+    employee_email_label: "Your email address: ",
+    supervisor_email_label: "Your supervisor's email address: ",
+    submit_review_title: "Stores the time sheet and creates a link \
+                          where it can be checked and countersigned.",
+    submit_review_button: "Submit for review",
+    preview_alt: "Preview",
+    profile_hint: "Name, staff ID, department, working area, working time and hourly wage \
+                   can be saved on the server to fill in the form next time. ",
+    save_profile_button: "Save",
+    export_profile_link: "Download saved data",
+    delete_profile_button: "Delete saved data",
+
+    tsg_intro_before: "You can also enter your data in a ",
+    tsg_intro_form: "cool form",
+    tsg_intro_after: ".",
+
+    review_title: "Time sheet {month} of {name}",
+    review_pending: "The time sheet is waiting to be reviewed.",
+    review_approved: "The time sheet was reviewed and approved by {name}.",
+    review_rejected: "The time sheet was rejected. Reason:",
+    download_submitted_pdf: "Download submitted PDF file",
+    download_approved_pdf: "Download reviewed PDF file",
+    approver_name_label: "Name: ",
+    approve_button: "Approve",
+    comment_label: "Reason: ",
+    reject_button: "Reject",
+
+    submitted_subject: "Time sheet {month} for review",
+    submitted_text: "{title} was submitted for review.",
+    approved_subject: "Time sheet {month} approved",
+    approved_text: "{title} was reviewed and approved by {name}.",
+    rejected_subject: "Time sheet {month} rejected",
+    rejected_text: "{title} was rejected. Reason:\n\n{comment}",
+
+    field_name: "name",
+    field_staff_id: "staff ID",
+    field_department: "unit",
+    field_hourly_wage: "hourly wage",
+    field_carry: "carry",
+    field_approver_name: "reviewer's name",
+    field_in_row: "{field} in row {row}",
+
+    invalid_working_area: "invalid working area: {value}",
+    invalid_working_time: "invalid working time: {value}",
+    invalid_month: "invalid month: {value}",
+    invalid_note: "invalid note: {value}",
+    incomplete_row: "row {row} is incomplete, missing: {fields}",
+    invalid_signature_date: "invalid signature date: {value}",
+    invalid_email: "invalid email address: {value}",
+    email_domain_not_allowed: "emails to addresses at {domain} are not sent",
+    invalid_certificate_file: "invalid certificate file",
+    invalid_certificate: "invalid certificate file or wrong password",
+    no_private_key: "certificate file contains no private key",
+    unsupported_key: "only RSA keys are supported",
+    no_certificate: "certificate file contains no certificate",
+    invalid_signature: "invalid signature: {error}",
+    sign_non_pdf: "only PDFs can be signed",
+    busy: "too many documents are being generated, try again later",
+    timeout: "generating the document took too long",
+    panicked: "generating the document failed unexpectedly",
+    profile_not_found: "no data saved",
+    too_many_profiles: "too many profiles were created recently, try again later",
+    too_many_entries: "too many entries: {entries} (at most {max} allowed)",
+    too_many_entries_for_template: "too many entries for template {template}: {entries} \
+                                    (at most {max} fit)",
+    field_too_long: "{field} is too long: {chars} characters (at most {max} allowed)",
+    invalid_character: "{field} contains invalid character {char}",
+    unknown_template: "unknown template: {value}",
+    invalid_signature_image: "invalid signature image, PNG and SVG are allowed",
+    unsupported_signature_image: "unsupported signature image type: {value}, \
+                                  PNG and SVG are allowed",
+    unknown_language: "unknown language: {value}",
+    invalid_standard: "invalid standard: {value}",
+    standard_not_supported: "standard is not supported for format {value}",
+    invalid_format: "invalid format: {value}",
+    invalid_dpi: "invalid dpi: {value} (at most {max} allowed)",
+    invalid_date: "invalid date: {value}",
+    invalid_pdf: "invalid pdf: {error}",
+    no_timesheet_data: "pdf contains no timesheet data",
+    invalid_timesheet_data: "invalid timesheet data: {error}",
+    signing_failed: "signing failed: {error}",
+    storage_failed: "accessing the database failed",
+    source_not_utf8: "source is not valid UTF-8",
+    sandbox_failed: "compiling the document failed unexpectedly",
+    sandbox_timeout: "compiling the document took too long",
+    sandbox_memory: "compiling the document was aborted, probably because it used too much memory",
+    review_not_found: "time sheet not found",
+    already_reviewed: "time sheet was already reviewed",
+    approver_name_missing: "name is missing",
+    invalid_approval_date: "invalid date: {value}",
+    comment_missing: "comment is missing",
+    invalid_decision: "invalid decision: {value}",
+
+    script: ScriptMessages {
+        generating: "Generating...",
+        generate_failed: "Generating failed:",
+        generate_succeeded: "Generating succeeded!",
+        file_name: "Timesheet.pdf",
+        read_failed: "Failed to read {file}:",
+        submitting: "Submitting...",
+        submit_failed: "Submitting failed:",
+        submit_succeeded: "Submitting succeeded!",
+        status_link: "Status",
+        review_link: "Link for the review",
+        review_link_hint: "Please only pass on the link for the review to your supervisor.",
+        preview_failed: "Preview failed:",
+        importing: "Importing...",
+        import_failed: "Importing failed:",
+        import_too_many_entries: "The file contains {entries} entries, but only {max} fit \
+                                  into the selected template.",
+        import_succeeded: "Importing succeeded!",
+        saving_profile: "Saving...",
+        save_profile_failed: "Saving failed:",
+        save_profile_succeeded: "Saving succeeded!",
+        delete_profile_failed: "Deleting failed:",
+        delete_profile_succeeded: "Deleted saved data!",
+    },
+};
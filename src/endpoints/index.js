@@ -1,6 +1,16 @@
+// Translated texts, provided by the page.
+const messages = window.messages;
 const form = document.getElementById("form");
 const submit = document.getElementById("submit");
 const info = document.getElementById("info");
+// Only present if there is more than one template.
+const templateSelect = document.getElementById("i-template");
+
+function endpoint(path) {
+  const url = new URL(path, form.action);
+  if (templateSelect) url.searchParams.set("template", templateSelect.value);
+  return url;
+}
 
 function showStatus(msg) {
   info.classList.remove("success");
@@ -24,19 +34,19 @@ function showError(msg) {
 }
 
 submit.addEventListener("click", async () => {
-  showStatus("Generiere...");
+  showStatus(messages.generating);
 
   const data = new FormData(form);
 
   try {
-    const response = await fetch(".", {
+    const response = await fetch(endpoint(""), {
       method: "post",
       body: new URLSearchParams(data),
     });
 
     if (response.status !== 200) {
       const reason = await response.text();
-      showError(`Generieren fehlgeschlagen:\n${reason}`);
+      showError(`${messages.generateFailed}\n${reason}`);
       return;
     }
 
@@ -46,17 +56,320 @@ submit.addEventListener("click", async () => {
     reader.addEventListener("loadend", () => {
       let element = document.createElement("a");
       element.setAttribute("href", reader.result);
-      element.setAttribute("download", "Arbeitszeitdokumentation.pdf");
+      element.setAttribute("download", messages.fileName);
 
       element.style.display = "none";
       document.body.appendChild(element);
       element.click();
       document.body.removeChild(element);
 
-      showSuccess("Generieren erfolgreich!");
+      showSuccess(messages.generateSucceeded);
     });
     reader.readAsDataURL(blob);
   } catch (e) {
-    showError(`Generieren fehlgeschlagen:\n${e}`);
+    showError(`${messages.generateFailed}\n${e}`);
+  }
+});
+
+// Only present if reviews are enabled.
+document.getElementById("submit-review")?.addEventListener("click", async () => {
+  showStatus(messages.submitting);
+
+  const data = new FormData(form);
+  // Submitted documents are signed by the server once they're approved.
+  data.delete("certificate");
+  data.delete("certificate_password");
+
+  try {
+    const response = await fetch(endpoint("submit"), {
+      method: "post",
+      body: new URLSearchParams(data),
+    });
+
+    if (response.status !== 200) {
+      const reason = await response.text();
+      showError(`${messages.submitFailed}\n${reason}`);
+      return;
+    }
+
+    const result = await response.json();
+    const statusUrl = new URL(result.status_url, location.href);
+    const reviewUrl = new URL(result.review_url, location.href);
+    showSuccess(
+      `${messages.submitSucceeded}\n\n` +
+        `${messages.statusLink}: ${statusUrl}\n` +
+        `${messages.reviewLink}: ${reviewUrl}\n\n` +
+        messages.reviewLinkHint,
+    );
+  } catch (e) {
+    showError(`${messages.submitFailed}\n${e}`);
+  }
+});
+
+// Only present if profiles are enabled.
+document.getElementById("save-profile")?.addEventListener("click", async () => {
+  showStatus(messages.savingProfile);
+
+  const data = new FormData(form);
+  // Only the header data is saved, so the rest doesn't need to be sent along.
+  data.delete("signature");
+  data.delete("certificate");
+  data.delete("certificate_password");
+
+  try {
+    const response = await fetch(new URL("profile", form.action), {
+      method: "post",
+      body: new URLSearchParams(data),
+    });
+
+    if (response.status !== 204) {
+      const reason = await response.text();
+      showError(`${messages.saveProfileFailed}\n${reason}`);
+      return;
+    }
+
+    showSuccess(messages.saveProfileSucceeded);
+  } catch (e) {
+    showError(`${messages.saveProfileFailed}\n${e}`);
+  }
+});
+
+// Only present if profiles are enabled.
+document.getElementById("delete-profile")?.addEventListener("click", async () => {
+  try {
+    const response = await fetch(new URL("profile", form.action), {
+      method: "delete",
+    });
+
+    if (response.status !== 204) {
+      const reason = await response.text();
+      showError(`${messages.deleteProfileFailed}\n${reason}`);
+      return;
+    }
+
+    showSuccess(messages.deleteProfileSucceeded);
+  } catch (e) {
+    showError(`${messages.deleteProfileFailed}\n${e}`);
+  }
+});
+
+const previewImage = document.getElementById("preview-image");
+const problems = document.getElementById("problems");
+const rowFields = ["task", "day", "start", "end", "rest", "note"];
+
+let previewTimeout;
+let previewAbort;
+
+function showProblems(list) {
+  for (const element of form.querySelectorAll(".problem")) {
+    element.classList.remove("problem");
+  }
+  problems.replaceChildren();
+
+  for (const problem of list) {
+    const item = document.createElement("li");
+    item.textContent = problem.message;
+    problems.appendChild(item);
+
+    for (const row of problem.rows) {
+      for (const field of rowFields) {
+        form.querySelectorAll(`[name="${field}"]`)[row]?.classList.add("problem");
+      }
+    }
+  }
+}
+
+async function updatePreview() {
+  previewAbort?.abort();
+  previewAbort = new AbortController();
+
+  const data = new FormData(form);
+  // Previews are never signed, so the key doesn't need to be sent along.
+  data.delete("certificate");
+  data.delete("certificate_password");
+
+  let result;
+  try {
+    const response = await fetch(endpoint("preview"), {
+      method: "post",
+      body: new URLSearchParams(data),
+      signal: previewAbort.signal,
+    });
+    result = await response.json();
+  } catch (e) {
+    if (e.name !== "AbortError") {
+      showProblems([{ message: `${messages.previewFailed} ${e}`, rows: [] }]);
+    }
+    return;
+  }
+
+  if (result.svg !== null) {
+    const blob = new Blob([result.svg], { type: "image/svg+xml" });
+    const oldSrc = previewImage.src;
+    previewImage.src = URL.createObjectURL(blob);
+    if (oldSrc) URL.revokeObjectURL(oldSrc);
+  }
+
+  showProblems(result.problems);
+}
+
+function schedulePreview() {
+  clearTimeout(previewTimeout);
+  previewTimeout = setTimeout(updatePreview, 500);
+}
+
+form.addEventListener("input", schedulePreview);
+form.addEventListener("change", schedulePreview);
+updatePreview();
+
+const table = document.getElementById("table");
+const addRow = document.getElementById("add-row");
+const removeRow = document.getElementById("remove-row");
+// Rows are not wrapped in an element, each field is a direct child of the table.
+const cellsPerRow = rowFields.length;
+// How many rows fit on each template, provided by the page.
+const templateRows = window.templateRows;
+
+function rowCount() {
+  return form.querySelectorAll('[name="task"]').length;
+}
+
+function capacity() {
+  // Without a selection, there is only one template.
+  return templateRows[templateSelect?.value] ?? Object.values(templateRows)[0];
+}
+
+function updateRowButtons() {
+  addRow.disabled = rowCount() >= capacity();
+  removeRow.disabled = rowCount() <= 1;
+}
+
+function appendRow() {
+  const cells = Array.from(table.children).slice(-cellsPerRow);
+  for (const cell of cells) {
+    const copy = cell.cloneNode(true);
+    copy.firstElementChild.value = "";
+    copy.firstElementChild.classList.remove("problem");
+    table.appendChild(copy);
+  }
+}
+
+addRow.addEventListener("click", () => {
+  appendRow();
+  updateRowButtons();
+  schedulePreview();
+});
+
+removeRow.addEventListener("click", () => {
+  for (let i = 0; i < cellsPerRow; i++) table.lastElementChild.remove();
+  updateRowButtons();
+  schedulePreview();
+});
+
+templateSelect?.addEventListener("change", updateRowButtons);
+updateRowButtons();
+
+const importInput = document.getElementById("i-import");
+const areaValues = { Großforschung: "GF", Unibereich: "UB" };
+const noteValues = { Urlaub: "U", Krankheit: "K", Feiertag: "F", Sonstiges: "S" };
+
+function field(name) {
+  return form.elements.namedItem(name);
+}
+
+function fillForm(ts) {
+  field("month").value = `${ts.year}-${String(ts.month).padStart(2, "0")}`;
+  field("name").value = ts.name;
+  field("staff_id").value = ts.staff_id;
+  field("working_area").value = areaValues[ts.working_area];
+  field("department").value = ts.department;
+  field("monthly_hours").value = ts.monthly_hours;
+  field("hourly_wage").value = ts.hourly_wage;
+  field("carry_prev_month").value = ts.carry_prev_month ?? "";
+  field("sort").checked = ts.sort;
+  field("validate").checked = ts.validate;
+  field("date_signature").checked = ts.date_signature ?? false;
+  field("signature_date").value = ts.signature_date ?? "";
+  field("language").value = ts.language ?? "de";
+
+  while (rowCount() < ts.entries.length) appendRow();
+  updateRowButtons();
+
+  const rows = {};
+  for (const name of rowFields) {
+    rows[name] = form.querySelectorAll(`[name="${name}"]`);
+    for (const element of rows[name]) element.value = "";
+  }
+
+  ts.entries.forEach((entry, i) => {
+    rows.task[i].value = entry.task;
+    rows.day[i].value = entry.day;
+    rows.start[i].value = entry.start;
+    rows.end[i].value = entry.end;
+    rows.rest[i].value = entry.rest ?? "";
+    rows.note[i].value = entry.note === null ? "" : noteValues[entry.note];
+  });
+}
+
+// The form is sent URL-encoded, so uploaded files are inlined as data URLs.
+function inlineFile(inputId, dataId) {
+  const input = document.getElementById(inputId);
+  const data = document.getElementById(dataId);
+
+  input.addEventListener("change", () => {
+    const file = input.files[0];
+    if (file === undefined) {
+      data.value = "";
+      schedulePreview();
+      return;
+    }
+
+    const reader = new FileReader();
+    reader.addEventListener("loadend", () => {
+      data.value = reader.result;
+      schedulePreview();
+    });
+    reader.readAsDataURL(file);
+  });
+}
+
+inlineFile("i-signature", "i-signature-data");
+inlineFile("i-certificate", "i-certificate-data");
+
+importInput.addEventListener("change", async () => {
+  const file = importInput.files[0];
+  if (file === undefined) return;
+
+  showStatus(messages.importing);
+
+  try {
+    const response = await fetch(new URL("import", form.action), {
+      method: "post",
+      body: file,
+    });
+
+    if (response.status !== 200) {
+      const reason = await response.text();
+      showError(`${messages.importFailed}\n${reason}`);
+      return;
+    }
+
+    const ts = await response.json();
+    if (ts.entries.length > capacity()) {
+      // Leaving out entries would silently lose data.
+      const reason = messages.importTooManyEntries
+        .replace("{entries}", ts.entries.length)
+        .replace("{max}", capacity());
+      showError(`${messages.importFailed}\n${reason}`);
+      return;
+    }
+
+    fillForm(ts);
+    showSuccess(messages.importSucceeded);
+    schedulePreview();
+  } catch (e) {
+    showError(`${messages.importFailed}\n${e}`);
+  } finally {
+    importInput.value = "";
   }
 });
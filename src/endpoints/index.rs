@@ -1,25 +1,61 @@
-use std::iter;
+use std::{collections::BTreeMap, iter, sync::Arc, time::Instant};
 
 use axum::{
-    http::{header, StatusCode},
+    body::Bytes,
+    extract::{Query, State},
+    http::HeaderMap,
     response::{IntoResponse, Response},
+    Json,
 };
 use axum_extra::extract::Form;
-use el::{html::*, Document};
+use el::{html::*, Document, Element};
 use jiff::{ToSpan, Zoned};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 
 use crate::{
-    endpoints::page,
-    render::{self, Entry, Note, Timesheet, WorkingArea},
+    config::Config,
+    endpoints::{
+        base_path, error_response, heading,
+        i18n::{self, fill, Lang, Messages},
+        page, pool_error_response, render_response, template, AppState, FormatQuery, TemplateQuery,
+    },
+    endpoints::{profile, review},
+    limits::LimitError,
+    mail,
+    render::{self, Entry, Language, Note, Signature, Template, Timesheet, WorkingArea},
+    sign::{self, Identity},
 };
 
-const LINK_SOURCE: &str = "https://github.com/Garmelon/Arbeitszeitdokumentationsgenerator";
 const LINK_TSG: &str = "https://github.com/kit-sdq/TimeSheetGenerator";
 const LINK_TEMPLATE: &str =
     "https://github.com/Garmelon/Arbeitszeitdokumentationsgenerator/blob/master/kit_timesheet.md";
 
-pub async fn get() -> Document {
+/// How many rows the form starts out with. More can be added in the browser, up
+/// to the capacity of the selected template.
+const INITIAL_ROWS: usize = 10;
+
+/// Tell the JS how many rows fit on each template.
+fn template_rows(templates: &[Arc<dyn Template>]) -> Element {
+    let rows = (templates.iter())
+        .map(|template| (template.name().to_string(), template.rows()))
+        .collect::<BTreeMap<_, _>>();
+    // Serializing plain data to JSON can't fail.
+    let json = serde_json::to_string(&rows).unwrap();
+    script(format!(
+        "window.templateRows = {};",
+        json.replace("</", "<\\/")
+    ))
+}
+
+pub async fn get(state: State<AppState>, headers: HeaderMap, lang: Lang) -> Document {
+    let config = &state.config;
+    let m = lang.messages();
+    let base = base_path(config, &headers);
+    let templates = state.templates.all();
+    let rows = INITIAL_ROWS.min(state.templates.default().rows());
+    let profile = profile::load(&state, &headers).await;
+
     // We assume that people still want to fill out the previous month's time
     // sheet during the first two weeks of the following month.
     let month = Zoned::now()
@@ -27,29 +63,83 @@ pub async fn get() -> Document {
         .unwrap()
         .strftime("%Y-%m");
 
+    // Fill in the saved profile, if there is one.
+    let defaults = &config.defaults;
+    let profile = profile.as_ref();
+    let name = profile.map_or("", |p| p.name.as_str());
+    let staff_id = profile.map_or("", |p| p.staff_id.as_str());
+    let department = profile.map_or(&defaults.department, |p| &p.department);
+    let monthly_hours = profile.map_or(defaults.monthly_hours, |p| p.monthly_hours);
+    let hourly_wage = profile.map_or(&defaults.hourly_wage, |p| &p.hourly_wage);
+    let gf = profile.is_some_and(|p| matches!(p.working_area, WorkingArea::Großforschung));
+
     let head = (
         style(include_str!("index.css")),
+        i18n::script_messages(lang),
+        template_rows(&templates),
         script((attr::TypeScript::Module, include_str!("index.js"))),
     );
 
-    let body = form((
+    let form = form((
         attr::id("form"),
-        h1((
-            "Arbeitszeitdokumentationsgenerator ",
-            a((attr::id("source"), attr::href(LINK_SOURCE), "(source)")),
+        attr::action(format!("{base}/")),
+        heading(config),
+        i18n::toggle(&base, lang, "index"),
+        (config.endpoints.tsg)
+            .then(|| {
+                p((
+                    m.intro_before,
+                    a((attr::href(format!("{base}/tsg/")), m.intro_json)),
+                    m.intro_tsg,
+                    a((attr::href(LINK_TSG), "TimeSheetGenerator")),
+                    m.intro_template_before,
+                    a((attr::href(LINK_TEMPLATE), m.intro_template)),
+                    m.intro_after,
+                ))
+            })
+            .into_iter()
+            .collect::<Vec<_>>(),
+        p((
+            label((
+                attr::r#for("i-import"),
+                attr::title(m.import_title),
+                m.import_label,
+            )),
+            input((
+                attr::id("i-import"),
+                attr::TypeInput::File,
+                attr::accept("application/pdf"),
+            )),
         )),
+        (templates.len() > 1)
+            .then(|| {
+                p((
+                    label((attr::r#for("i-template"), m.template_label)),
+                    select((
+                        attr::id("i-template"),
+                        (templates.iter())
+                            .map(|template| {
+                                option((attr::value(template.name()), template.title()))
+                            })
+                            .collect::<Vec<_>>(),
+                    )),
+                ))
+            })
+            .into_iter()
+            .collect::<Vec<_>>(),
         p((
-            "Du kannst auch ",
-            a((attr::href("tsg/"), "JSON eingeben")),
-            ", das kompatibel mit dem ",
-            a((attr::href(LINK_TSG), "TimeSheetGenerator")),
-            " ist, oder das dem Generator zugrunde liegende ",
-            a((attr::href(LINK_TEMPLATE), "Typst-Template")),
-            " direkt benutzen.",
+            label((attr::r#for("i-language"), m.document_language_label)),
+            select((
+                attr::id("i-language"),
+                attr::name("language"),
+                option((attr::value("de"), m.document_language_de)),
+                option((attr::value("en"), m.document_language_en)),
+                option((attr::value("de-en"), m.document_language_de_en)),
+            )),
         )),
         div((
             attr::id("header"),
-            label((attr::id("l-month"), attr::r#for("i-month"), "Monat / Jahr:")),
+            label((attr::id("l-month"), attr::r#for("i-month"), m.month_label)),
             input((
                 attr::id("i-month"),
                 attr::name("month"),
@@ -57,73 +147,72 @@ pub async fn get() -> Document {
                 attr::placeholder(&month),
                 attr::value(&month),
             )),
-            label((
-                attr::id("l-name"),
-                attr::r#for("i-name"),
-                "Name, Vorname des/r Beschäftigten:",
-            )),
+            label((attr::id("l-name"), attr::r#for("i-name"), m.name_label)),
             input((
                 attr::id("i-name"),
                 attr::class("twocol"),
                 attr::name("name"),
                 attr::TypeInput::Text,
                 attr::placeholder("McStudentface, Student"),
+                attr::value(name),
             )),
             label((
                 attr::id("l-staffid"),
                 attr::r#for("i-staffid"),
-                "Personalnummer:",
+                m.staff_id_label,
             )),
             input((
                 attr::id("i-staffid"),
                 attr::name("staff_id"),
                 attr::TypeInput::Text,
                 attr::placeholder("1337420"),
+                attr::value(staff_id),
             )),
             div((
                 attr::id("gfub"),
                 label((
                     attr::id("l-gf"),
-                    attr::title("Großforschung"),
+                    attr::title(m.gf_title),
                     "GF: ",
                     input((
                         attr::id("i-gf"),
                         attr::name("working_area"),
                         attr::TypeInput::Radio,
                         attr::value("GF"),
+                        gf.then(attr::checked).into_iter().collect::<Vec<_>>(),
                     )),
                 )),
                 label((
                     attr::id("l-ub"),
-                    attr::title("Unibereich"),
+                    attr::title(m.ub_title),
                     "UB: ",
                     input((
                         attr::id("i-ub"),
                         attr::name("working_area"),
                         attr::TypeInput::Radio,
                         attr::value("UB"),
-                        attr::checked(),
+                        (!gf).then(attr::checked).into_iter().collect::<Vec<_>>(),
                     )),
                 )),
             )),
             label((
                 attr::id("l-department"),
                 attr::r#for("i-department"),
-                attr::title("Institut/Organisationseinheit"),
-                "OE:",
+                attr::title(m.department_title),
+                m.department_label,
             )),
             input((
                 attr::id("i-department"),
                 attr::class("twocol"),
                 attr::name("department"),
                 attr::TypeInput::Text,
-                attr::placeholder("Institut für Informatik"),
-                attr::value("Institut für Informatik"),
+                attr::placeholder(&defaults.department),
+                attr::value(department),
             )),
             label((
                 attr::id("l-monthlyhours"),
                 attr::r#for("i-monthlyhours"),
-                "Vertraglich vereinbarte Arbeitszeit:",
+                m.monthly_hours_label,
             )),
             div((
                 attr::id("mhhr"),
@@ -133,23 +222,23 @@ pub async fn get() -> Document {
                         attr::id("i-monthlyhours"),
                         attr::name("monthly_hours"),
                         attr::TypeInput::Number,
-                        attr::value(40),
+                        attr::value(monthly_hours),
                         attr::min(0),
                     )),
-                    " Std.",
+                    m.hours_unit,
                 )),
                 span((
                     label((
                         attr::id("l-hourlywage"),
                         attr::r#for("i-hourlywage"),
-                        "Stundensatz: ",
+                        m.hourly_wage_label,
                     )),
                     input((
                         attr::id("i-hourlywage"),
                         attr::name("hourly_wage"),
                         attr::TypeInput::Number,
                         attr::step(0.01),
-                        attr::value(14.09),
+                        attr::value(hourly_wage),
                     )),
                     " €",
                 )),
@@ -158,11 +247,7 @@ pub async fn get() -> Document {
                 attr::id("carry"),
                 attr::class("twocol"),
                 span((
-                    label((
-                        attr::id("l-carry"),
-                        attr::r#for("i-carry"),
-                        "Übertrag vom Vormonat: ",
-                    )),
+                    label((attr::id("l-carry"), attr::r#for("i-carry"), m.carry_label)),
                     input((
                         attr::id("i-carry"),
                         attr::class("i-dur"),
@@ -172,48 +257,126 @@ pub async fn get() -> Document {
                     )),
                 )),
             )),
-            label((
-                attr::id("check"),
-                attr::title(concat!(
-                    "Die Tabelleneinträge werden chronologisch sortiert,",
-                    " anstatt dass ihre Reihenfolge beibehalten wird."
+            // el only supports tuples of up to 16 components, nested ones are
+            // added to the same element.
+            (
+                label((
+                    attr::id("check"),
+                    attr::title(m.sort_title),
+                    m.sort_label,
+                    input((
+                        attr::name("sort"),
+                        attr::TypeInput::Checkbox,
+                        attr::value(true),
+                        attr::checked(),
+                    )),
                 )),
-                "Einträge sortieren ",
-                input((
-                    attr::name("sort"),
-                    attr::TypeInput::Checkbox,
-                    attr::value(true),
-                    attr::checked(),
+                label((
+                    attr::id("validate"),
+                    attr::title(m.validate_title),
+                    m.validate_label,
+                    input((
+                        attr::name("validate"),
+                        attr::TypeInput::Checkbox,
+                        attr::value(true),
+                        attr::checked(),
+                    )),
                 )),
-            )),
-            label((
-                attr::id("validate"),
-                attr::title(concat!(
-                    "Die Tabelleneinträge werden auf Konsistenz und Korrektheit überprüft,",
-                    " bevor das Dokument generiert wird."
+                label((
+                    attr::id("sign"),
+                    attr::title(m.date_signature_title),
+                    m.date_signature_label,
+                    input((
+                        attr::name("date_signature"),
+                        attr::TypeInput::Checkbox,
+                        attr::value(true),
+                    )),
+                )),
+                span((
+                    attr::id("signature"),
+                    label((
+                        attr::r#for("i-signature-date"),
+                        attr::title(m.signature_date_title),
+                        m.signature_date_label,
+                    )),
+                    input((
+                        attr::id("i-signature-date"),
+                        attr::name("signature_date"),
+                        attr::TypeInput::Date,
+                    )),
+                    label((
+                        attr::r#for("i-signature"),
+                        attr::title(m.signature_title),
+                        m.signature_label,
+                    )),
+                    input((
+                        attr::id("i-signature"),
+                        attr::TypeInput::File,
+                        attr::accept("image/png,image/svg+xml"),
+                    )),
+                    input((
+                        attr::id("i-signature-data"),
+                        attr::name("signature"),
+                        attr::TypeInput::Hidden,
+                    )),
                 )),
-                "Einträge validieren ",
-                input((
-                    attr::name("validate"),
-                    attr::TypeInput::Checkbox,
-                    attr::value(true),
-                    attr::checked(),
+                span((
+                    attr::id("certificate"),
+                    label((
+                        attr::r#for("i-certificate"),
+                        attr::title(m.certificate_title),
+                        m.certificate_label,
+                    )),
+                    input((
+                        attr::id("i-certificate"),
+                        attr::TypeInput::File,
+                        attr::accept(".p12,.pfx,application/x-pkcs12"),
+                    )),
+                    input((
+                        attr::id("i-certificate-data"),
+                        attr::name("certificate"),
+                        attr::TypeInput::Hidden,
+                    )),
+                    label((
+                        attr::r#for("i-certificate-password"),
+                        m.certificate_password_label,
+                    )),
+                    input((
+                        attr::id("i-certificate-password"),
+                        attr::name("certificate_password"),
+                        attr::TypeInput::Password,
+                    )),
                 )),
-            )),
+            ),
         )),
+        (config.endpoints.profiles)
+            .then(|| {
+                p((
+                    attr::id("profile"),
+                    m.profile_hint,
+                    button((
+                        attr::id("save-profile"),
+                        attr::TypeButton::Button,
+                        m.save_profile_button,
+                    )),
+                    a((attr::href(format!("{base}/profile")), m.export_profile_link)),
+                    button((
+                        attr::id("delete-profile"),
+                        attr::TypeButton::Button,
+                        m.delete_profile_button,
+                    )),
+                ))
+            })
+            .into_iter()
+            .collect::<Vec<_>>(),
         div((
             attr::id("table"),
-            div((
-                attr::id("task"),
-                "Tätigkeit",
-                br(()),
-                "(Stichwort, Projekt)",
-            )),
-            div("Tag"),
-            div("Beginn"),
-            div("Ende"),
-            div("Pause"),
-            div("Arbeitszeit"),
+            div((attr::id("task"), m.task_header, br(()), m.task_header_hint)),
+            div(m.day_header),
+            div(m.start_header),
+            div(m.end_header),
+            div(m.rest_header),
+            div(m.duration_header),
             div(()),
             div("(hh:mm)"),
             div("(hh:mm)"),
@@ -255,26 +418,77 @@ pub async fn get() -> Document {
                     div(select((
                         attr::name("note"),
                         attr::value(""),
-                        option((attr::value(""), "Normal")),
-                        option((attr::value("U"), "Urlaub")),
-                        option((attr::value("K"), "Krankheit")),
-                        option((attr::value("F"), "Feiertag")),
-                        option((attr::value("S"), "Sonstiges")),
+                        option((attr::value(""), m.note_normal)),
+                        option((attr::value("U"), m.note_urlaub)),
+                        option((attr::value("K"), m.note_krankheit)),
+                        option((attr::value("F"), m.note_feiertag)),
+                        option((attr::value("S"), m.note_sonstiges)),
                     ))),
                 ),
-                22,
+                rows,
             )
             .collect::<Vec<_>>(),
         )),
+        div((
+            attr::id("rows"),
+            button((
+                attr::id("add-row"),
+                attr::TypeButton::Button,
+                m.add_row_button,
+            )),
+            button((
+                attr::id("remove-row"),
+                attr::TypeButton::Button,
+                m.remove_row_button,
+            )),
+        )),
         button((
             attr::id("submit"),
             attr::TypeButton::Button,
-            "Arbeitszeitdokumentation generieren",
+            m.generate_button,
         )),
+        (config.endpoints.review)
+            .then(|| {
+                // Without notifications, there's no use for email addresses.
+                let emails = config.notifications().then(|| {
+                    (
+                        label((attr::r#for("i-employee-email"), m.employee_email_label)),
+                        input((
+                            attr::id("i-employee-email"),
+                            attr::name("employee_email"),
+                            attr::TypeInput::Email,
+                        )),
+                        label((attr::r#for("i-supervisor-email"), m.supervisor_email_label)),
+                        input((
+                            attr::id("i-supervisor-email"),
+                            attr::name("supervisor_email"),
+                            attr::TypeInput::Email,
+                        )),
+                    )
+                });
+                div((
+                    attr::id("review"),
+                    emails.into_iter().collect::<Vec<_>>(),
+                    button((
+                        attr::id("submit-review"),
+                        attr::TypeButton::Button,
+                        attr::title(m.submit_review_title),
+                        m.submit_review_button,
+                    )),
+                ))
+            })
+            .into_iter()
+            .collect::<Vec<_>>(),
         pre(attr::id("info")),
     ));
 
-    page(head, body)
+    let preview = aside((
+        attr::id("preview"),
+        img((attr::id("preview-image"), attr::alt(m.preview_alt))),
+        ul(attr::id("problems")),
+    ));
+
+    page(config, head, (form, preview))
 }
 
 #[derive(Debug, Deserialize)]
@@ -291,6 +505,26 @@ pub struct PostForm {
     sort: bool,
     #[serde(default)]
     validate: bool,
+    #[serde(default)]
+    date_signature: bool,
+    #[serde(default)]
+    signature_date: String,
+    /// A `data:` URL, filled in by the JS from the selected file.
+    #[serde(default)]
+    signature: String,
+    /// A PKCS#12 file as `data:` URL, like the signature.
+    #[serde(default)]
+    certificate: String,
+    #[serde(default)]
+    certificate_password: String,
+    /// Only used when submitting for review.
+    #[serde(default)]
+    employee_email: String,
+    /// Only used when submitting for review.
+    #[serde(default)]
+    supervisor_email: String,
+    #[serde(default)]
+    language: String,
     task: Vec<String>,
     day: Vec<Option<u32>>,
     start: Vec<String>,
@@ -299,10 +533,6 @@ pub struct PostForm {
     note: Vec<String>,
 }
 
-fn error_response<S: ToString>(msg: S) -> Response {
-    (StatusCode::BAD_REQUEST, msg.to_string()).into_response()
-}
-
 fn parse_month(month_str: &str) -> Option<(u32, u32)> {
     let mut parts = month_str.split('-');
 
@@ -316,21 +546,100 @@ fn parse_month(month_str: &str) -> Option<(u32, u32)> {
     Some((year, month))
 }
 
-pub async fn post(form: Form<PostForm>) -> Response {
-    let form = form.0;
+impl PostForm {
+    fn clear_row(&mut self, row: usize) {
+        // Only called for rows found by `incomplete_rows`, which exist in
+        // every field.
+        self.task[row].clear();
+        self.day[row] = None;
+        self.start[row].clear();
+        self.end[row].clear();
+        self.rest[row].clear();
+        self.note[row].clear();
+    }
+}
+
+/// Find rows that were filled out only partially, returning their (0-based)
+/// index and a message naming the missing fields. Only rows where every field,
+/// including rest and note, is blank are ignored.
+fn incomplete_rows(form: &PostForm, m: &Messages) -> Vec<(usize, String)> {
+    let fields = (form.task.iter())
+        .zip(&form.day)
+        .zip(&form.start)
+        .zip(&form.end)
+        .zip(&form.rest)
+        .zip(&form.note)
+        .enumerate();
+
+    let mut incomplete = vec![];
+    for (row, (((((task, day), start), end), rest), note)) in fields {
+        let blank = task.is_empty()
+            && day.is_none()
+            && start.is_empty()
+            && end.is_empty()
+            && rest.is_empty()
+            && note.is_empty();
+        if blank {
+            continue;
+        }
 
+        let missing = [
+            (task.is_empty(), m.task_header),
+            (day.is_none(), m.day_header),
+            (start.is_empty(), m.start_header),
+            (end.is_empty(), m.end_header),
+        ]
+        .into_iter()
+        .filter(|(missing, _)| *missing)
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>();
+        if missing.is_empty() {
+            continue;
+        }
+
+        let message = fill(
+            m.incomplete_row,
+            &[("row", &(row + 1)), ("fields", &missing.join(", "))],
+        );
+        incomplete.push((row, message));
+    }
+    incomplete
+}
+
+/// Convert the form into a [`Timesheet`], also returning the (0-based) form
+/// row each entry came from.
+fn timesheet(
+    form: PostForm,
+    holiday_state: &str,
+    m: &Messages,
+) -> Result<(Timesheet, Vec<usize>), String> {
     // Parse working area
     let working_area = match &form.working_area as &str {
         "GF" => WorkingArea::Großforschung,
         "UB" => WorkingArea::Unibereich,
-        _ => return error_response(format!("invalid working area: {:?}", form.working_area)),
+        value => {
+            return Err(fill(
+                m.invalid_working_area,
+                &[("value", &format!("{value:?}"))],
+            ))
+        }
     };
 
     // Parse month
     let Some((year, month)) = parse_month(&form.month) else {
-        return error_response(format!("invalid month: {:?}", form.month));
+        let value = format!("{:?}", form.month);
+        return Err(fill(m.invalid_month, &[("value", &value)]));
     };
 
+    // Check rows
+    let incomplete = incomplete_rows(&form, m);
+    if !incomplete.is_empty() {
+        let messages = (incomplete.into_iter())
+            .map(|(_, message)| message)
+            .collect::<Vec<_>>();
+        return Err(messages.join("\n"));
+    }
+
     // Parse rests
     let rests = form
         .rest
@@ -347,11 +656,34 @@ pub async fn post(form: Form<PostForm>) -> Response {
             "K" => Some(Note::Krankheit),
             "F" => Some(Note::Feiertag),
             "S" => Some(Note::Sonstiges),
-            _ => return error_response(format!("invalid note: {note:?}")),
+            _ => return Err(fill(m.invalid_note, &[("value", &format!("{note:?}"))])),
         };
         notes.push(note)
     }
 
+    // Parse signature
+    let signature_date = if form.signature_date.is_empty() {
+        None
+    } else {
+        let date = (form.signature_date.parse()).map_err(|_| {
+            let value = format!("{:?}", form.signature_date);
+            fill(m.invalid_signature_date, &[("value", &value)])
+        })?;
+        Some(date)
+    };
+    let signature = if form.signature.is_empty() {
+        None
+    } else {
+        let signature = Signature::parse_data_url(&form.signature).map_err(|e| e.message(m))?;
+        Some(signature)
+    };
+
+    // Parse language
+    let language = match &form.language as &str {
+        "" => Language::German,
+        language => Language::parse(language).map_err(|e| e.message(m))?,
+    };
+
     // Parse carry
     let carry_prev_month = if form.carry_prev_month.is_empty() {
         None
@@ -359,26 +691,30 @@ pub async fn post(form: Form<PostForm>) -> Response {
         Some(form.carry_prev_month)
     };
 
-    let entries = (form.task.into_iter())
-        .zip(form.day.into_iter())
-        .zip(form.start.into_iter())
-        .zip(form.end.into_iter())
-        .zip(rests.into_iter())
-        .zip(notes.into_iter())
-        .filter_map(|(((((task, day), start), end), rest), note)| {
-            if task.is_empty() || start.is_empty() || end.is_empty() {
-                return None;
-            };
-            Some(Entry {
-                task,
-                day: day?,
-                start,
-                end,
-                rest,
-                note,
-            })
-        })
-        .collect::<Vec<_>>();
+    let mut rows = vec![];
+    let mut entries = vec![];
+    let fields = (form.task.into_iter())
+        .zip(form.day)
+        .zip(form.start)
+        .zip(form.end)
+        .zip(rests)
+        .zip(notes)
+        .enumerate();
+    for (row, (((((task, day), start), end), rest), note)) in fields {
+        // Incomplete rows were rejected above, so this row is blank.
+        let Some(day) = day else {
+            continue;
+        };
+        rows.push(row);
+        entries.push(Entry {
+            task,
+            day,
+            start,
+            end,
+            rest,
+            note,
+        });
+    }
 
     let timesheet = Timesheet {
         name: form.name,
@@ -392,11 +728,280 @@ pub async fn post(form: Form<PostForm>) -> Response {
         carry_prev_month,
         year,
         month,
+        holiday_state: holiday_state.to_string(),
+        date_signature: form.date_signature,
+        signature_date,
+        signature,
+        approval: None,
+        language,
         entries,
     };
 
-    match render::render(timesheet) {
-        Ok(pdf) => ([(header::CONTENT_TYPE, "application/pdf")], pdf).into_response(),
-        Err(errors) => error_response(errors.join("\n")),
+    Ok((timesheet, rows))
+}
+
+/// The certificate to sign the PDF with, if one was uploaded.
+fn identity(form: &PostForm, m: &Messages) -> Result<Option<Identity>, String> {
+    if form.certificate.is_empty() {
+        return Ok(None);
+    }
+    let (_, data) = render::parse_data_url(&form.certificate).ok_or(m.invalid_certificate_file)?;
+    Identity::from_pkcs12(&data, &form.certificate_password)
+        .map(Some)
+        .map_err(|e| e.message(m).to_string())
+}
+
+pub async fn post(
+    state: State<AppState>,
+    query: Query<FormatQuery>,
+    lang: Lang,
+    form: Form<PostForm>,
+) -> Response {
+    let identity = match identity(&form, lang.messages()) {
+        Ok(identity) => identity,
+        Err(error) => {
+            info!(category = "signing", "invalid certificate");
+            state.metrics.count_render("index", "signing");
+            return error_response(error);
+        }
+    };
+
+    match timesheet(form.0, &state.config.holiday_state, lang.messages()) {
+        Ok((timesheet, _)) => {
+            render_response(&state, "index", timesheet, &query, identity, lang).await
+        }
+        Err(error) => {
+            info!(category = "input", "invalid form");
+            state.metrics.count_render("index", "input");
+            error_response(error)
+        }
+    }
+}
+
+/// Only addresses at the configured domains are accepted, so nobody can make
+/// the server send emails elsewhere.
+fn email(address: &str, domains: &[String], m: &Messages) -> Result<Option<String>, String> {
+    let address = address.trim();
+    if address.is_empty() {
+        return Ok(None);
+    }
+    mail::check_address(address)
+        .map_err(|_| fill(m.invalid_email, &[("value", &format!("{address:?}"))]))?;
+    if !mail::has_domain(address, domains) {
+        let domain = address.rsplit_once('@').map_or("", |(_, domain)| domain);
+        return Err(fill(m.email_domain_not_allowed, &[("domain", &domain)]));
+    }
+    Ok(Some(address.to_string()))
+}
+
+/// Convert the form into a [`Timesheet`] along with the employee's and the
+/// supervisor's email address.
+fn submission(
+    form: PostForm,
+    config: &Config,
+    m: &Messages,
+) -> Result<(Timesheet, Option<String>, Option<String>), String> {
+    let domains = &config.review.email_domains;
+    let employee_email = email(&form.employee_email, domains, m)?;
+    let supervisor_email = email(&form.supervisor_email, domains, m)?;
+    let (timesheet, _) = timesheet(form, &config.holiday_state, m)?;
+    Ok((timesheet, employee_email, supervisor_email))
+}
+
+pub async fn submit(
+    state: State<AppState>,
+    headers: HeaderMap,
+    query: Query<TemplateQuery>,
+    lang: Lang,
+    form: Form<PostForm>,
+) -> Response {
+    match submission(form.0, &state.config, lang.messages()) {
+        Ok((timesheet, employee_email, supervisor_email)) => {
+            review::submit(
+                &state,
+                &headers,
+                query.template.as_deref(),
+                timesheet,
+                employee_email,
+                supervisor_email,
+            )
+            .await
+        }
+        Err(error) => {
+            info!(category = "input", "invalid form");
+            state.metrics.count_render("submit", "input");
+            error_response(error)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PreviewProblem {
+    message: String,
+    /// The (0-based) form rows the problem refers to.
+    rows: Vec<usize>,
+}
+
+#[derive(Serialize)]
+pub struct PreviewJson {
+    svg: Option<String>,
+    problems: Vec<PreviewProblem>,
+}
+
+pub async fn preview(
+    state: State<AppState>,
+    query: Query<TemplateQuery>,
+    lang: Lang,
+    form: Form<PostForm>,
+) -> Response {
+    // Incomplete rows are reported, but left out of the preview so it doesn't
+    // disappear while a row is being filled out.
+    let m = lang.messages();
+    let mut form = form.0;
+    let incomplete = incomplete_rows(&form, m);
+    for (row, _) in &incomplete {
+        form.clear_row(*row);
+    }
+    let mut problems = (incomplete.into_iter())
+        .map(|(row, message)| PreviewProblem {
+            message,
+            rows: vec![row],
+        })
+        .collect::<Vec<_>>();
+
+    let checked = timesheet(form, &state.config.holiday_state, m)
+        .map_err(|message| ("input", message))
+        .and_then(
+            |(timesheet, rows)| match state.config.limits.check(&timesheet, m) {
+                Ok(()) => Ok((timesheet, rows)),
+                Err(error) => {
+                    let category = error.category();
+                    let (LimitError::TooLarge(message) | LimitError::Invalid(message)) = error;
+                    Err((category, message))
+                }
+            },
+        )
+        .and_then(|(timesheet, rows)| {
+            match template(&state, query.template.as_deref(), &timesheet, m) {
+                Ok(template) => Ok((template, timesheet, rows)),
+                Err(message) => Err(("input", message)),
+            }
+        });
+
+    let (template, timesheet, rows) = match checked {
+        Ok(result) => result,
+        Err((category, message)) => {
+            state.metrics.count_render("preview", category);
+            problems.push(PreviewProblem {
+                message,
+                rows: vec![],
+            });
+            return Json(PreviewJson {
+                svg: None,
+                problems,
+            })
+            .into_response();
+        }
+    };
+
+    let days = timesheet.entries.iter().map(|e| e.day).collect::<Vec<_>>();
+    let start = Instant::now();
+    // Only a fixed date can fail to parse.
+    let now = render::now(state.config.time_zone(), None).unwrap();
+    let preview = match state
+        .pool
+        .run(move || render::preview(&*template, timesheet, now))
+        .await
+    {
+        Ok(preview) => preview,
+        Err(error) => {
+            warn!(category = error.category(), "preview failed");
+            state.metrics.count_render("preview", error.category());
+            return pool_error_response(error, lang);
+        }
+    };
+    info!(
+        entries = days.len(),
+        duration = ?start.elapsed(),
+        problems = preview.problems.len(),
+        "rendered preview"
+    );
+    state.metrics.count_render("preview", "success");
+
+    problems.extend(preview.problems.into_iter().map(|problem| {
+        let problem_rows = match (problem.row, problem.day) {
+            (Some(row), _) => (row.checked_sub(1))
+                .and_then(|i| rows.get(i))
+                .copied()
+                .into_iter()
+                .collect(),
+            (None, Some(day)) => (rows.iter().zip(&days))
+                .filter(|(_, d)| **d == day)
+                .map(|(row, _)| *row)
+                .collect(),
+            (None, None) => vec![],
+        };
+        PreviewProblem {
+            message: problem.message,
+            rows: problem_rows,
+        }
+    }));
+
+    Json(PreviewJson {
+        svg: preview.svg,
+        problems,
+    })
+    .into_response()
+}
+
+pub async fn import(lang: Lang, pdf: Bytes) -> Response {
+    match render::extract(&pdf) {
+        Ok(timesheet) => {
+            info!(entries = timesheet.entries.len(), "imported pdf");
+            Json(timesheet).into_response()
+        }
+        Err(error) => {
+            info!(category = "import", "import failed");
+            error_response(error.message(lang.messages()))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct VerificationJson {
+    signer: Option<String>,
+    signed_at: Option<String>,
+    intact: bool,
+    covers_whole_document: bool,
+    /// Always `false`, since nothing checks whether the signer's certificate
+    /// can be trusted. Spelled out so clients don't take `intact` for that.
+    trusted: bool,
+}
+
+#[derive(Serialize)]
+pub struct VerifyJson {
+    signatures: Vec<VerificationJson>,
+}
+
+pub async fn verify(lang: Lang, pdf: Bytes) -> Response {
+    match sign::verify_pdf(&pdf) {
+        Ok(verifications) => {
+            info!(signatures = verifications.len(), "verified pdf");
+            let signatures = verifications
+                .into_iter()
+                .map(|verification| VerificationJson {
+                    signer: verification.signer,
+                    signed_at: verification.signed_at,
+                    intact: verification.intact,
+                    covers_whole_document: verification.covers_whole_document,
+                    trusted: false,
+                })
+                .collect();
+            Json(VerifyJson { signatures }).into_response()
+        }
+        Err(error) => {
+            info!(category = "verify", "verification failed");
+            error_response(error.message(lang.messages()))
+        }
     }
 }
@@ -0,0 +1,21 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{endpoints::AppState, metrics::Active};
+
+pub async fn get(state: State<AppState>) -> Response {
+    match state.metrics.encode() {
+        Ok(text) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text).into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+    }
+}
+
+/// Tracks the number of requests currently being handled.
+pub async fn track_requests(state: State<AppState>, request: Request, next: Next) -> Response {
+    let _active = Active::new(state.metrics.requests_active.clone());
+    next.run(request).await
+}
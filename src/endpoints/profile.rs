@@ -0,0 +1,237 @@
+use std::{
+    net::{IpAddr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::Config,
+    endpoints::{
+        base_path, cookie, error_response,
+        i18n::{fill, Lang},
+        is_https, limit_error_response, storage_error_response, AppState, ClientAddr,
+    },
+    profiles::{Profile, Profiles},
+    rate_limit::RateLimiter,
+    render::WorkingArea,
+};
+
+/// How long the browser keeps the profile's token, in seconds. Saving the
+/// profile again renews it. Profiles that weren't saved for this long can't be
+/// used anymore and are deleted.
+const COOKIE_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// How often expired profiles are deleted.
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub struct ProfileState {
+    pub profiles: Profiles,
+    /// Limits how many profiles each client creates. Clients whose address is
+    /// unknown, e.g. on a Unix socket without a trusted proxy, share a limit.
+    pub create_limit: RateLimiter,
+}
+
+impl ProfileState {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        Ok(Self {
+            profiles: Profiles::open(&config.profiles.database)?,
+            create_limit: RateLimiter::new(
+                config.profiles.max_created_per_hour,
+                Duration::from_secs(60 * 60),
+            ),
+        })
+    }
+
+    /// Periodically delete the profiles whose cookie has expired.
+    pub async fn expire(self: Arc<Self>) {
+        let max_age = Duration::from_secs(COOKIE_MAX_AGE);
+        let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match self.profiles.delete_expired(max_age).await {
+                Ok(0) => {}
+                Ok(deleted) => info!(deleted, "deleted expired profiles"),
+                Err(error) => warn!(%error, "deleting expired profiles failed"),
+            }
+        }
+    }
+}
+
+fn profile_state(state: &AppState) -> &ProfileState {
+    // The routes are only added when profiles are enabled.
+    state.profiles.as_ref().unwrap()
+}
+
+fn profiles(state: &AppState) -> &Profiles {
+    &profile_state(state).profiles
+}
+
+fn token_cookie(state: &AppState, headers: &HeaderMap, token: &str, max_age: u64) -> String {
+    let base = base_path(&state.config, headers);
+    let secure = match is_https(&state.config, headers) {
+        true => "; Secure",
+        false => "",
+    };
+    format!("profile={token}; Path={base}/; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}")
+}
+
+/// The key clients are rate limited by. IPv6 clients usually get a whole /64
+/// network, so they are told apart by that. Clients of unknown address all
+/// get the empty key.
+fn limit_key(client: &ClientAddr) -> String {
+    // Dual-stack sockets report IPv4 clients as IPv4-mapped IPv6 addresses.
+    match client.0.map(|addr| addr.to_canonical()) {
+        Some(IpAddr::V4(addr)) => addr.to_string(),
+        Some(IpAddr::V6(addr)) => {
+            let network = u128::from(addr) & !(u128::MAX >> 64);
+            format!("{}/64", Ipv6Addr::from(network))
+        }
+        None => String::new(),
+    }
+}
+
+/// Create a profile unless too many were created recently.
+async fn create(
+    state: &AppState,
+    client: &ClientAddr,
+    profile: &Profile,
+    lang: Lang,
+) -> Result<String, Response> {
+    let profile_state = profile_state(state);
+    if !profile_state.create_limit.check(&limit_key(client)) {
+        warn!("too many profiles created, not creating another");
+        let msg = lang.messages().too_many_profiles;
+        return Err((StatusCode::TOO_MANY_REQUESTS, msg).into_response());
+    }
+    (profile_state.profiles.create(profile).await).map_err(|e| storage_error_response(e, lang))
+}
+
+fn not_found_response(lang: Lang) -> Response {
+    (StatusCode::NOT_FOUND, lang.messages().profile_not_found).into_response()
+}
+
+/// The profile saved by whoever sent the request, if any.
+pub async fn load(state: &AppState, headers: &HeaderMap) -> Option<Profile> {
+    let profiles = &state.profiles.as_ref()?.profiles;
+    let token = cookie(headers, "profile")?;
+    match profiles.get(token).await {
+        Ok(stored) => stored.map(|stored| stored.profile),
+        Err(error) => {
+            // The form works without a profile, so this isn't fatal.
+            error!(%error, "database query failed");
+            None
+        }
+    }
+}
+
+/// Export everything stored about the profile.
+pub async fn get(state: State<AppState>, headers: HeaderMap, lang: Lang) -> Response {
+    let Some(token) = cookie(&headers, "profile") else {
+        return not_found_response(lang);
+    };
+    match profiles(&state).get(token).await {
+        Ok(Some(stored)) => {
+            info!("exported profile");
+            (
+                [(
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"profile.json\"",
+                )],
+                Json(stored),
+            )
+                .into_response()
+        }
+        Ok(None) => not_found_response(lang),
+        Err(error) => storage_error_response(error, lang),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileForm {
+    name: String,
+    staff_id: String,
+    working_area: String,
+    department: String,
+    monthly_hours: u32,
+    hourly_wage: String,
+}
+
+/// Save the header data of the form, creating a new profile if the request
+/// doesn't belong to one yet.
+pub async fn post(
+    state: State<AppState>,
+    headers: HeaderMap,
+    client: ClientAddr,
+    lang: Lang,
+    form: Form<ProfileForm>,
+) -> Response {
+    let form = form.0;
+    let working_area = match &form.working_area as &str {
+        "GF" => WorkingArea::Großforschung,
+        "UB" => WorkingArea::Unibereich,
+        value => {
+            let value = format!("{value:?}");
+            return error_response(fill(
+                lang.messages().invalid_working_area,
+                &[("value", &value)],
+            ));
+        }
+    };
+
+    let profile = Profile {
+        name: form.name,
+        staff_id: form.staff_id,
+        department: form.department,
+        working_area,
+        monthly_hours: form.monthly_hours,
+        hourly_wage: form.hourly_wage,
+    };
+    if let Err(error) = state.config.limits.check_profile(&profile, lang.messages()) {
+        info!(category = error.category(), "profile exceeds limits");
+        return limit_error_response(error);
+    }
+
+    let result = match cookie(&headers, "profile") {
+        Some(token) => match profiles(&state).update(token, &profile).await {
+            Ok(true) => Ok(token.to_string()),
+            // The profile was deleted in the meantime.
+            Ok(false) => create(&state, &client, &profile, lang).await,
+            Err(error) => Err(storage_error_response(error, lang)),
+        },
+        None => create(&state, &client, &profile, lang).await,
+    };
+    let token = match result {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    info!("saved profile");
+
+    let cookie = token_cookie(&state, &headers, &token, COOKIE_MAX_AGE);
+    (StatusCode::NO_CONTENT, [(header::SET_COOKIE, cookie)]).into_response()
+}
+
+/// Delete the profile and make the browser forget its token.
+pub async fn delete(state: State<AppState>, headers: HeaderMap, lang: Lang) -> Response {
+    let Some(token) = cookie(&headers, "profile") else {
+        return not_found_response(lang);
+    };
+    match profiles(&state).delete(token).await {
+        Ok(true) => {
+            info!("deleted profile");
+            let cookie = token_cookie(&state, &headers, "", 0);
+            (StatusCode::NO_CONTENT, [(header::SET_COOKIE, cookie)]).into_response()
+        }
+        Ok(false) => not_found_response(lang),
+        Err(error) => storage_error_response(error, lang),
+    }
+}
@@ -0,0 +1,17 @@
+// The form is sent URL-encoded, so the signature is inlined as data URL.
+const input = document.getElementById("i-signature");
+const data = document.getElementById("i-signature-data");
+
+input?.addEventListener("change", () => {
+  const file = input.files[0];
+  if (file === undefined) {
+    data.value = "";
+    return;
+  }
+
+  const reader = new FileReader();
+  reader.addEventListener("loadend", () => {
+    data.value = reader.result;
+  });
+  reader.readAsDataURL(file);
+});
@@ -0,0 +1,534 @@
+use std::{fs, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use axum_extra::extract::Form;
+use el::{html::*, Element};
+use jiff::tz::TimeZone;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    config::Config,
+    endpoints::{
+        base_path, error_response, heading,
+        i18n::{fill, Lang, Messages},
+        limit_error_response, page, pool_error_response, storage_error_response, template,
+        AppState,
+    },
+    mail::Mailer,
+    rate_limit::RateLimiter,
+    render::{self, Approval, Format, Signature, Timesheet},
+    review::{Review, Reviews, Status},
+    sign::{self, Identity},
+};
+
+pub struct ReviewState {
+    pub reviews: Reviews,
+    /// Only present if notifications are enabled.
+    pub mailer: Option<Mailer>,
+    /// Limits the emails sent to each address.
+    pub email_limit: RateLimiter,
+    /// Signs approved time sheets.
+    pub identity: Option<Identity>,
+}
+
+impl ReviewState {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let reviews = Reviews::open(&config.review.database)?;
+        let mailer = match (&config.smtp, config.notifications()) {
+            (Some(smtp), true) => Some(Mailer::new(smtp)?),
+            _ => None,
+        };
+        let email_limit = RateLimiter::new(
+            config.review.max_emails_per_hour,
+            Duration::from_secs(60 * 60),
+        );
+        let identity = match &config.review.certificate {
+            Some(path) => {
+                let data = fs::read(path)
+                    .with_context(|| format!("failed to read certificate {}", path.display()))?;
+                let identity = Identity::from_pkcs12(&data, &config.review.certificate_password)
+                    .map_err(|e| anyhow!("failed to load certificate {}: {e}", path.display()))?;
+                Some(identity)
+            }
+            None => None,
+        };
+        Ok(Self {
+            reviews,
+            mailer,
+            email_limit,
+            identity,
+        })
+    }
+}
+
+fn review_state(state: &AppState) -> &Arc<ReviewState> {
+    // The routes are only added when reviews are enabled.
+    state.review.as_ref().unwrap()
+}
+
+fn not_found_response(lang: Lang) -> Response {
+    (StatusCode::NOT_FOUND, lang.messages().review_not_found).into_response()
+}
+
+fn already_decided_response(lang: Lang) -> Response {
+    (StatusCode::CONFLICT, lang.messages().already_reviewed).into_response()
+}
+
+fn month(timesheet: &Timesheet) -> String {
+    format!("{:02}/{}", timesheet.month, timesheet.year)
+}
+
+fn describe(timesheet: &Timesheet, m: &Messages) -> String {
+    fill(
+        m.review_title,
+        &[("month", &month(timesheet)), ("name", &timesheet.name)],
+    )
+}
+
+/// Send an email in the background, linking to a path below the public URL.
+/// Does nothing if no mail server is configured.
+///
+/// The subject only names the month, since anybody submitting a time sheet
+/// can choose the name freely and subjects are shown before the recipient
+/// decides to open the email.
+fn notify(state: &AppState, to: Option<String>, subject: String, text: String, path: String) {
+    let review = review_state(state).clone();
+    let (Some(to), Some(_)) = (to, &review.mailer) else {
+        return;
+    };
+    if !review.email_limit.check(&to.to_lowercase()) {
+        warn!("too many notifications to the same address, not sending");
+        return;
+    }
+    // Validated when loading the config
+    let public_url = state.config.review.public_url.clone().unwrap();
+    let body = format!("{text}\n\n{public_url}{path}\n");
+
+    tokio::spawn(async move {
+        let mailer = review.mailer.as_ref().unwrap();
+        match mailer.send(&to, &subject, body).await {
+            Ok(()) => info!("sent notification"),
+            Err(error) => warn!(%error, "sending notification failed"),
+        }
+    });
+}
+
+/// Render a timesheet as PDF, signing it with the server's certificate if
+/// requested and configured.
+async fn render_pdf(
+    state: &AppState,
+    endpoint: &str,
+    template_name: Option<&str>,
+    timesheet: Timesheet,
+    countersign: bool,
+    lang: Lang,
+) -> Result<Vec<u8>, Response> {
+    let m = lang.messages();
+    if let Err(error) = state.config.limits.check(&timesheet, m) {
+        info!(category = error.category(), "input exceeds limits");
+        state.metrics.count_render(endpoint, error.category());
+        return Err(limit_error_response(error));
+    }
+
+    let template = match template(state, template_name, &timesheet, m) {
+        Ok(template) => template,
+        Err(error) => {
+            info!(category = "input", "invalid template");
+            state.metrics.count_render(endpoint, "input");
+            return Err(error_response(error));
+        }
+    };
+
+    // Only a fixed date can fail to parse.
+    let now = render::now(state.config.time_zone(), None).unwrap();
+    let review = review_state(state).clone();
+    let result = state
+        .pool
+        .run(move || {
+            let format = Format::Pdf { standard: None };
+            let pdf = render::render(&*template, timesheet, format, now)
+                .map_err(|es| ("typst", es.join("\n")))?;
+            match &review.identity {
+                Some(identity) if countersign => sign::sign_pdf(&pdf, identity)
+                    .map_err(|e| ("signing", fill(m.signing_failed, &[("error", &e)]))),
+                _ => Ok(pdf),
+            }
+        })
+        .await;
+
+    match result {
+        Ok(Ok(pdf)) => {
+            state.metrics.count_render(endpoint, "success");
+            Ok(pdf)
+        }
+        Ok(Err((category, message))) => {
+            info!(category, "rendering failed");
+            state.metrics.count_render(endpoint, category);
+            Err(error_response(message))
+        }
+        Err(error) => {
+            warn!(category = error.category(), "rendering failed");
+            state.metrics.count_render(endpoint, error.category());
+            Err(pool_error_response(error, lang))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SubmitJson {
+    /// Where the employee can follow the review.
+    status_url: String,
+    /// Where the supervisor can approve or reject the time sheet.
+    review_url: String,
+}
+
+/// Store a time sheet for review and notify the supervisor.
+pub async fn submit(
+    state: &AppState,
+    headers: &HeaderMap,
+    template_name: Option<&str>,
+    timesheet: Timesheet,
+    employee_email: Option<String>,
+    supervisor_email: Option<String>,
+) -> Response {
+    // The employee's signature must keep the date it was submitted on.
+    let mut timesheet = timesheet;
+    if timesheet.date_signature && timesheet.signature_date.is_none() {
+        // Only a fixed date can fail to parse.
+        let now = render::now(state.config.time_zone(), None).unwrap();
+        timesheet.signature_date = Some(now.date());
+    }
+
+    let lang = Lang::from_headers(headers);
+    let m = lang.messages();
+    let entries = timesheet.entries.len();
+    // Approving must use the same template, even if the default changes.
+    let template_name = template_name
+        .map(str::to_string)
+        .unwrap_or_else(|| state.templates.default().name().to_string());
+    let pdf = match render_pdf(
+        state,
+        "submit",
+        Some(&template_name),
+        timesheet.clone(),
+        false,
+        lang,
+    )
+    .await
+    {
+        Ok(pdf) => pdf,
+        Err(response) => return response,
+    };
+
+    let stored = review_state(state)
+        .reviews
+        .submit(
+            &template_name,
+            &timesheet,
+            pdf,
+            employee_email,
+            supervisor_email.clone(),
+        )
+        .await;
+    let (id, token) = match stored {
+        Ok(ids) => ids,
+        Err(error) => return storage_error_response(error, lang),
+    };
+    info!(entries, "submitted time sheet for review");
+
+    // The supervisor's language is unknown, so this uses the employee's.
+    let title = describe(&timesheet, m);
+    notify(
+        state,
+        supervisor_email,
+        fill(m.submitted_subject, &[("month", &month(&timesheet))]),
+        fill(m.submitted_text, &[("title", &title)]),
+        format!("/review/{token}"),
+    );
+
+    let base = base_path(&state.config, headers);
+    Json(SubmitJson {
+        status_url: format!("{base}/reviews/{id}"),
+        review_url: format!("{base}/review/{token}"),
+    })
+    .into_response()
+}
+
+fn status_text(review: &Review, m: &Messages) -> Element {
+    match review.status {
+        Status::Pending => p(m.review_pending),
+        Status::Approved => p(fill(
+            m.review_approved,
+            &[("name", &review.approved_by.as_deref().unwrap_or(""))],
+        )),
+        Status::Rejected => div((
+            p(m.review_rejected),
+            pre(review.comment.clone().unwrap_or_default()),
+        )),
+    }
+}
+
+fn pdf_link(base: &str, review: &Review, m: &Messages) -> Element {
+    let text = match review.status {
+        Status::Approved => m.download_approved_pdf,
+        Status::Pending | Status::Rejected => m.download_submitted_pdf,
+    };
+    p(a((
+        attr::href(format!("{base}/reviews/{}/pdf", review.id)),
+        text,
+    )))
+}
+
+pub async fn status(
+    state: State<AppState>,
+    headers: HeaderMap,
+    lang: Lang,
+    Path(id): Path<String>,
+) -> Response {
+    let review = match review_state(&state).reviews.get(&id).await {
+        Ok(Some(review)) => review,
+        Ok(None) => return not_found_response(lang),
+        Err(error) => return storage_error_response(error, lang),
+    };
+
+    let config = &state.config;
+    let m = lang.messages();
+    let base = base_path(config, &headers);
+    let body = (
+        heading(config),
+        h2(describe(&review.timesheet, m)),
+        status_text(&review, m),
+        pdf_link(&base, &review, m),
+    );
+    page(config, style(include_str!("review.css")), body).into_response()
+}
+
+pub async fn pdf(state: State<AppState>, lang: Lang, Path(id): Path<String>) -> Response {
+    match review_state(&state).reviews.get(&id).await {
+        Ok(Some(review)) => {
+            ([(header::CONTENT_TYPE, "application/pdf")], review.pdf).into_response()
+        }
+        Ok(None) => not_found_response(lang),
+        Err(error) => storage_error_response(error, lang),
+    }
+}
+
+pub async fn get(
+    state: State<AppState>,
+    headers: HeaderMap,
+    lang: Lang,
+    Path(token): Path<String>,
+) -> Response {
+    let review = match review_state(&state).reviews.get_by_token(&token).await {
+        Ok(Some(review)) => review,
+        Ok(None) => return not_found_response(lang),
+        Err(error) => return storage_error_response(error, lang),
+    };
+
+    let config = &state.config;
+    let m = lang.messages();
+    let base = base_path(config, &headers);
+    let action = format!("{base}/review/{token}");
+    // Only a fixed date can fail to parse.
+    let today = render::now(config.time_zone(), None).unwrap().date();
+
+    let forms = (review.status == Status::Pending).then(|| {
+        (
+            form((
+                attr::id("approve"),
+                attr::Method::Post,
+                attr::action(&action),
+                input((
+                    attr::name("decision"),
+                    attr::TypeInput::Hidden,
+                    attr::value("approve"),
+                )),
+                label((attr::r#for("i-name"), m.approver_name_label)),
+                input((
+                    attr::id("i-name"),
+                    attr::name("name"),
+                    attr::TypeInput::Text,
+                )),
+                label((attr::r#for("i-date"), m.signature_date_label)),
+                input((
+                    attr::id("i-date"),
+                    attr::name("date"),
+                    attr::TypeInput::Date,
+                    attr::value(today.to_string()),
+                )),
+                label((
+                    attr::r#for("i-signature"),
+                    attr::title(m.signature_title),
+                    m.signature_label,
+                )),
+                input((
+                    attr::id("i-signature"),
+                    attr::TypeInput::File,
+                    attr::accept("image/png,image/svg+xml"),
+                )),
+                input((
+                    attr::id("i-signature-data"),
+                    attr::name("signature"),
+                    attr::TypeInput::Hidden,
+                )),
+                button((attr::TypeButton::Submit, m.approve_button)),
+            )),
+            form((
+                attr::id("reject"),
+                attr::Method::Post,
+                attr::action(&action),
+                input((
+                    attr::name("decision"),
+                    attr::TypeInput::Hidden,
+                    attr::value("reject"),
+                )),
+                label((attr::r#for("i-comment"), m.comment_label)),
+                textarea((attr::id("i-comment"), attr::name("comment"))),
+                button((attr::TypeButton::Submit, m.reject_button)),
+            )),
+        )
+    });
+
+    let head = (
+        style(include_str!("review.css")),
+        script((attr::TypeScript::Module, include_str!("review.js"))),
+    );
+    let body = (
+        heading(config),
+        h2(describe(&review.timesheet, m)),
+        status_text(&review, m),
+        pdf_link(&base, &review, m),
+        forms.into_iter().collect::<Vec<_>>(),
+    );
+    page(config, head, body).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct DecisionForm {
+    decision: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    date: String,
+    /// A `data:` URL, filled in by the JS from the selected file.
+    #[serde(default)]
+    signature: String,
+    #[serde(default)]
+    comment: String,
+}
+
+fn approval(form: &DecisionForm, time_zone: TimeZone, m: &Messages) -> Result<Approval, String> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(m.approver_name_missing.to_string());
+    }
+
+    let date = if form.date.is_empty() {
+        // Only a fixed date can fail to parse.
+        render::now(time_zone, None).unwrap().date()
+    } else {
+        (form.date.parse()).map_err(|_| {
+            let value = format!("{:?}", form.date);
+            fill(m.invalid_approval_date, &[("value", &value)])
+        })?
+    };
+
+    let signature = if form.signature.is_empty() {
+        None
+    } else {
+        let signature = Signature::parse_data_url(&form.signature).map_err(|e| e.message(m))?;
+        Some(signature)
+    };
+
+    Ok(Approval {
+        name: name.to_string(),
+        date,
+        signature,
+    })
+}
+
+pub async fn post(
+    state: State<AppState>,
+    headers: HeaderMap,
+    lang: Lang,
+    Path(token): Path<String>,
+    form: Form<DecisionForm>,
+) -> Response {
+    let reviews = &review_state(&state).reviews;
+    let review = match reviews.get_by_token(&token).await {
+        Ok(Some(review)) => review,
+        Ok(None) => return not_found_response(lang),
+        Err(error) => return storage_error_response(error, lang),
+    };
+    if review.status != Status::Pending {
+        return already_decided_response(lang);
+    }
+
+    // The employee's language is unknown, so this uses the supervisor's.
+    let m = lang.messages();
+    let title = describe(&review.timesheet, m);
+    let month = month(&review.timesheet);
+    let (decided, subject, text) = match form.decision.as_str() {
+        "approve" => {
+            let approval = match approval(&form, state.config.time_zone(), m) {
+                Ok(approval) => approval,
+                Err(error) => {
+                    info!(category = "input", "invalid approval");
+                    state.metrics.count_render("review", "input");
+                    return error_response(error);
+                }
+            };
+            let name = approval.name.clone();
+            let timesheet = Timesheet {
+                approval: Some(approval),
+                ..review.timesheet
+            };
+            let template_name = Some(review.template.as_str());
+            let pdf = match render_pdf(&state, "review", template_name, timesheet, true, lang).await
+            {
+                Ok(pdf) => pdf,
+                Err(response) => return response,
+            };
+            (
+                reviews.approve(&review.id, &name, pdf).await,
+                fill(m.approved_subject, &[("month", &month)]),
+                fill(m.approved_text, &[("title", &title), ("name", &name)]),
+            )
+        }
+        "reject" => {
+            let comment = form.comment.trim();
+            if comment.is_empty() {
+                info!(category = "input", "rejection without comment");
+                return error_response(m.comment_missing);
+            }
+            (
+                reviews.reject(&review.id, comment).await,
+                fill(m.rejected_subject, &[("month", &month)]),
+                fill(m.rejected_text, &[("title", &title), ("comment", &comment)]),
+            )
+        }
+        decision => {
+            let value = format!("{decision:?}");
+            return error_response(fill(m.invalid_decision, &[("value", &value)]));
+        }
+    };
+
+    match decided {
+        Ok(true) => {}
+        Ok(false) => return already_decided_response(lang),
+        Err(error) => return storage_error_response(error, lang),
+    }
+    info!(decision = form.decision.as_str(), "reviewed time sheet");
+
+    let path = format!("/reviews/{}", review.id);
+    notify(&state, review.employee_email, subject, text, path.clone());
+
+    Redirect::to(&format!("{}{path}", base_path(&state.config, &headers))).into_response()
+}
@@ -1,3 +1,5 @@
+// Translated texts, provided by the page.
+const messages = window.messages;
 const form = document.getElementById("form");
 const submit = document.getElementById("submit");
 const info = document.getElementById("info");
@@ -24,7 +26,7 @@ function showError(msg) {
 }
 
 submit.addEventListener("click", async () => {
-  showStatus("Generiere...");
+  showStatus(messages.generating);
 
   const data = new FormData(form);
 
@@ -32,7 +34,8 @@ submit.addEventListener("click", async () => {
   try {
     global = JSON.parse(data.get("global"));
   } catch (e) {
-    showError(`Generieren fehlgeschlagen:\nFailed to read Global.json:\n${e}`);
+    const reason = messages.readFailed.replace("{file}", "Global.json");
+    showError(`${messages.generateFailed}\n${reason}\n${e}`);
     return;
   }
 
@@ -40,21 +43,23 @@ submit.addEventListener("click", async () => {
   try {
     month = JSON.parse(data.get("month"));
   } catch (e) {
-    showError(`Generieren fehlgeschlagen:\nFailed to read Month.json:\n${e}`);
+    const reason = messages.readFailed.replace("{file}", "Month.json");
+    showError(`${messages.generateFailed}\n${reason}\n${e}`);
     return;
   }
 
   let sort = data.get("sort") !== null;
   let validate = data.get("validate") !== null;
+  let language = data.get("language");
 
   console.log("data", data)
   console.log("sort", sort)
   console.log("validate", validate)
 
-  const dataJson = JSON.stringify({ global, month, sort, validate });
+  const dataJson = JSON.stringify({ global, month, sort, validate, language });
 
   try {
-    const response = await fetch(".", {
+    const response = await fetch(form.action, {
       method: "post",
       headers: { "Content-Type": "application/json" },
       body: dataJson,
@@ -62,7 +67,7 @@ submit.addEventListener("click", async () => {
 
     if (response.status !== 200) {
       const reason = await response.text();
-      showError(`Generieren fehlgeschlagen:\n${reason}`);
+      showError(`${messages.generateFailed}\n${reason}`);
       return;
     }
 
@@ -72,17 +77,17 @@ submit.addEventListener("click", async () => {
     reader.addEventListener("loadend", () => {
       let element = document.createElement("a");
       element.setAttribute("href", reader.result);
-      element.setAttribute("download", "Arbeitszeitdokumentation.pdf");
+      element.setAttribute("download", messages.fileName);
 
       element.style.display = "none";
       document.body.appendChild(element);
       element.click();
       document.body.removeChild(element);
 
-      showSuccess("Generieren erfolgreich!");
+      showSuccess(messages.generateSucceeded);
     });
     reader.readAsDataURL(blob);
   } catch (e) {
-    showError(`Generieren fehlgeschlagen:\n${e}`);
+    showError(`${messages.generateFailed}\n${e}`);
   }
 });
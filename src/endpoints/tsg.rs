@@ -1,41 +1,88 @@
 use axum::{
-    http::{header, StatusCode},
-    response::{IntoResponse, Response},
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Response,
     Json,
 };
 use el::{html::*, Document};
 use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
 
 use crate::{
-    endpoints::page,
-    render::{self, Entry, Note, Timesheet, WorkingArea},
+    endpoints::{
+        base_path, error_response, heading,
+        i18n::{self, fill, Lang, Messages},
+        page, profile, render_response, AppState, FormatQuery,
+    },
+    profiles::Profile,
+    render::{Entry, Language, Note, Timesheet, WorkingArea},
 };
 
-const LINK_SOURCE: &str = "https://github.com/Garmelon/Arbeitszeitdokumentationsgenerator";
+/// A `Global.json` with the data from a saved profile.
+fn global_json(profile: &Profile) -> String {
+    // Keep values the TimeSheetGenerator wouldn't accept as they are, so the
+    // error points at them.
+    let staff_id = match profile.staff_id.parse::<i64>() {
+        Ok(staff_id) => json!(staff_id),
+        Err(_) => json!(profile.staff_id),
+    };
+    let wage = match profile.hourly_wage.parse::<f64>() {
+        Ok(wage) => json!(wage),
+        Err(_) => json!(profile.hourly_wage),
+    };
+    let working_area = match profile.working_area {
+        WorkingArea::Großforschung => "gf",
+        WorkingArea::Unibereich => "ub",
+    };
+    let global = json!({
+        "name": profile.name,
+        "staffId": staff_id,
+        "department": profile.department,
+        "workingTime": format!("{}:00", profile.monthly_hours),
+        "wage": wage,
+        "workingArea": working_area,
+    });
+    // Serializing plain data to JSON can't fail.
+    serde_json::to_string_pretty(&global).unwrap()
+}
+
+pub async fn get(state: State<AppState>, headers: HeaderMap, lang: Lang) -> Document {
+    let config = &state.config;
+    let m = lang.messages();
+    let base = base_path(config, &headers);
+    let global = (profile::load(&state, &headers).await)
+        .map(|profile| global_json(&profile))
+        .unwrap_or_default();
 
-pub async fn get() -> Document {
     let head = (
         style(include_str!("tsg.css")),
+        i18n::script_messages(lang),
         script((attr::TypeScript::Module, include_str!("tsg.js"))),
     );
 
     let body = form((
         attr::id("form"),
-        h1((
-            "Arbeitszeitdokumentationsgenerator ",
-            a((attr::id("source"), attr::href(LINK_SOURCE), "(source)")),
-        )),
-        p((
-            "Du kannst deine Daten auch in einem ",
-            a((attr::href(".."), "coolen Formular")),
-            " eingeben.",
-        )),
+        attr::action(format!("{base}/tsg/")),
+        heading(config),
+        i18n::toggle(&base, lang, "tsg"),
+        (config.endpoints.index)
+            .then(|| {
+                p((
+                    m.tsg_intro_before,
+                    a((attr::href(format!("{base}/")), m.tsg_intro_form)),
+                    m.tsg_intro_after,
+                ))
+            })
+            .into_iter()
+            .collect::<Vec<_>>(),
         p((
             label((attr::r#for("i-global"), "Global.json")),
             textarea((
                 attr::id("i-global"),
                 attr::name("global"),
                 attr::placeholder("{}"),
+                global,
             )),
         )),
         p((
@@ -48,39 +95,45 @@ pub async fn get() -> Document {
         )),
         p((
             label((
-                attr::title(concat!(
-                    "Die Einträge werden chronologisch sortiert,",
-                    " anstatt dass ihre Reihenfolge beibehalten wird."
-                )),
+                attr::title(m.sort_title),
                 input((
                     attr::name("sort"),
                     attr::TypeInput::Checkbox,
                     attr::checked(),
                 )),
-                " Einträge sortieren",
+                " ",
+                m.sort_label,
             )),
             label((
-                attr::title(concat!(
-                    "Die Einträge werden auf Konsistenz und Korrektheit überprüft,",
-                    " bevor das Dokument generiert wird."
-                )),
+                attr::title(m.validate_title),
                 input((
                     attr::name("validate"),
                     attr::TypeInput::Checkbox,
                     attr::checked(),
                 )),
-                " Einträge validieren",
+                " ",
+                m.validate_label,
+            )),
+        )),
+        p((
+            label((attr::r#for("i-language"), m.document_language_label)),
+            select((
+                attr::id("i-language"),
+                attr::name("language"),
+                option((attr::value("de"), m.document_language_de)),
+                option((attr::value("en"), m.document_language_en)),
+                option((attr::value("de-en"), m.document_language_de_en)),
             )),
         )),
         button((
             attr::id("submit"),
             attr::TypeButton::Button,
-            "Arbeitszeitdokumentation generieren",
+            m.generate_button,
         )),
         pre(attr::id("info")),
     ));
 
-    page(head, body)
+    page(config, head, body)
 }
 
 fn default_vacation() -> bool {
@@ -123,10 +176,8 @@ pub struct PostJson {
     month: MonthJson,
     sort: bool,
     validate: bool,
-}
-
-fn error_response<S: ToString>(msg: S) -> Response {
-    (StatusCode::BAD_REQUEST, msg.to_string()).into_response()
+    #[serde(default)]
+    language: Language,
 }
 
 fn parse_span(span_str: &str) -> Option<u32> {
@@ -146,31 +197,35 @@ fn parse_span(span_str: &str) -> Option<u32> {
     Some(hours)
 }
 
-pub async fn post(json: Json<PostJson>) -> Response {
-    let json = json.0;
-
+/// Convert TimeSheetGenerator-compatible data into a [`Timesheet`].
+pub fn timesheet(
+    global: GlobalJson,
+    month: MonthJson,
+    sort: bool,
+    validate: bool,
+    holiday_state: String,
+    language: Language,
+    m: &Messages,
+) -> Result<Timesheet, String> {
     // Parse working area
-    let working_area = match &json.global.working_area as &str {
+    let working_area = match &global.working_area as &str {
         "gf" => WorkingArea::Großforschung,
         "ub" => WorkingArea::Unibereich,
-        _ => {
-            return error_response(format!(
-                "invalid working area: {:?}",
-                json.global.working_area
+        value => {
+            return Err(fill(
+                m.invalid_working_area,
+                &[("value", &format!("{value:?}"))],
             ))
         }
     };
 
     // Parse working time
-    let Some(monthly_hours) = parse_span(&json.global.working_time) else {
-        return error_response(format!(
-            "invalid working_time: {:?}",
-            json.global.working_time
-        ));
+    let Some(monthly_hours) = parse_span(&global.working_time) else {
+        let value = format!("{:?}", global.working_time);
+        return Err(fill(m.invalid_working_time, &[("value", &value)]));
     };
 
-    let entries = json
-        .month
+    let entries = month
         .entries
         .into_iter()
         .map(|e| Entry {
@@ -183,23 +238,51 @@ pub async fn post(json: Json<PostJson>) -> Response {
         })
         .collect::<Vec<_>>();
 
-    let timesheet = Timesheet {
-        name: json.global.name,
-        staff_id: json.global.staff_id.to_string(),
-        department: json.global.department,
+    Ok(Timesheet {
+        name: global.name,
+        staff_id: global.staff_id.to_string(),
+        department: global.department,
         working_area,
         monthly_hours,
-        hourly_wage: json.global.wage.to_string(),
-        validate: json.validate,
-        sort: json.sort,
-        carry_prev_month: json.month.pred_transfer,
-        year: json.month.year,
-        month: json.month.month,
+        hourly_wage: global.wage.to_string(),
+        validate,
+        sort,
+        carry_prev_month: month.pred_transfer,
+        year: month.year,
+        month: month.month,
+        holiday_state,
+        date_signature: false,
+        signature_date: None,
+        signature: None,
+        approval: None,
+        language,
         entries,
-    };
+    })
+}
 
-    match render::render(timesheet) {
-        Ok(pdf) => ([(header::CONTENT_TYPE, "application/pdf")], pdf).into_response(),
-        Err(errors) => error_response(errors.join("\n")),
+pub async fn post(
+    state: State<AppState>,
+    query: Query<FormatQuery>,
+    lang: Lang,
+    json: Json<PostJson>,
+) -> Response {
+    let json = json.0;
+
+    let holiday_state = state.config.holiday_state.clone();
+    match timesheet(
+        json.global,
+        json.month,
+        json.sort,
+        json.validate,
+        holiday_state,
+        json.language,
+        lang.messages(),
+    ) {
+        Ok(timesheet) => render_response(&state, "tsg", timesheet, &query, None, lang).await,
+        Err(error) => {
+            info!(category = "input", "invalid json");
+            state.metrics.count_render("tsg", "input");
+            error_response(error)
+        }
     }
 }
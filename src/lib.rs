@@ -0,0 +1,17 @@
+//! The web server and CLI live in `main.rs`. Everything else is a library so
+//! that the tests in `tests/` can use it.
+
+pub mod config;
+pub mod db;
+pub mod endpoints;
+pub mod limits;
+pub mod listen;
+pub mod mail;
+pub mod metrics;
+pub mod pool;
+pub mod profiles;
+pub mod rate_limit;
+pub mod render;
+pub mod review;
+pub mod sandbox;
+pub mod sign;
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+use crate::{
+    endpoints::i18n::{fill, Messages},
+    profiles::Profile,
+    render::Timesheet,
+};
+
+pub enum LimitError {
+    /// The input is too large to be processed.
+    TooLarge(String),
+    /// The input contains characters that aren't allowed.
+    Invalid(String),
+}
+
+impl LimitError {
+    /// Short name for logs and metrics.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::TooLarge(_) => "too_large",
+            Self::Invalid(_) => "invalid_chars",
+        }
+    }
+}
+
+/// Bounds on user input, checked before it ends up in a typst compile.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub max_body_bytes: usize,
+    pub max_entries: usize,
+    pub max_field_chars: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 1024 * 1024,
+            max_entries: 64,
+            max_field_chars: 256,
+        }
+    }
+}
+
+impl Limits {
+    fn check_field(&self, field: &str, value: &str, m: &Messages) -> Result<(), LimitError> {
+        let chars = value.chars().count();
+        if chars > self.max_field_chars {
+            return Err(LimitError::TooLarge(fill(
+                m.field_too_long,
+                &[
+                    ("field", &field),
+                    ("chars", &chars),
+                    ("max", &self.max_field_chars),
+                ],
+            )));
+        }
+
+        if let Some(c) = value.chars().find(|c| c.is_control()) {
+            let c = format!("{c:?}");
+            return Err(LimitError::Invalid(fill(
+                m.invalid_character,
+                &[("field", &field), ("char", &c)],
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn check(&self, ts: &Timesheet, m: &Messages) -> Result<(), LimitError> {
+        if ts.entries.len() > self.max_entries {
+            return Err(LimitError::TooLarge(fill(
+                m.too_many_entries,
+                &[("entries", &ts.entries.len()), ("max", &self.max_entries)],
+            )));
+        }
+
+        self.check_field(m.field_name, &ts.name, m)?;
+        self.check_field(m.field_staff_id, &ts.staff_id, m)?;
+        self.check_field(m.field_department, &ts.department, m)?;
+        self.check_field(m.field_hourly_wage, &ts.hourly_wage, m)?;
+        if let Some(carry) = &ts.carry_prev_month {
+            self.check_field(m.field_carry, carry, m)?;
+        }
+        if let Some(approval) = &ts.approval {
+            self.check_field(m.field_approver_name, &approval.name, m)?;
+        }
+
+        for (i, entry) in ts.entries.iter().enumerate() {
+            let row = i + 1;
+            let field = |name: &str| fill(m.field_in_row, &[("field", &name), ("row", &row)]);
+            self.check_field(&field(m.task_header), &entry.task, m)?;
+            self.check_field(&field(m.start_header), &entry.start, m)?;
+            self.check_field(&field(m.end_header), &entry.end, m)?;
+            if let Some(rest) = &entry.rest {
+                self.check_field(&field(m.rest_header), rest, m)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn check_profile(&self, profile: &Profile, m: &Messages) -> Result<(), LimitError> {
+        self.check_field(m.field_name, &profile.name, m)?;
+        self.check_field(m.field_staff_id, &profile.staff_id, m)?;
+        self.check_field(m.field_department, &profile.department, m)?;
+        self.check_field(m.field_hourly_wage, &profile.hourly_wage, m)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    env,
+    fmt::Debug,
+    fs,
+    future::IntoFuture,
+    io,
+    net::SocketAddr,
+    os::{
+        fd::{FromRawFd, IntoRawFd, RawFd},
+        unix::fs::FileTypeExt,
+    },
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use axum::{extract::connect_info::Connected, serve::IncomingStream, Router};
+use tokio::{
+    net::{TcpListener, UnixListener},
+    signal::unix::{signal, SignalKind},
+    sync::Notify,
+};
+use tracing::{info, warn};
+
+/// Prefix of addresses that refer to a Unix domain socket, e.g.
+/// `unix:/run/abzdokgen.sock`.
+const UNIX_PREFIX: &str = "unix:";
+
+/// The first file descriptor passed by systemd, see sd_listen_fds(3).
+const LISTEN_FDS_START: RawFd = 3;
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// The address of the connection's peer, available to handlers as
+/// `ConnectInfo<PeerAddr>`. Unknown on Unix domain sockets, whose peers
+/// usually don't have an address.
+#[derive(Clone, Copy)]
+pub struct PeerAddr(pub Option<SocketAddr>);
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self(Some(*stream.remote_addr()))
+    }
+}
+
+impl Connected<IncomingStream<'_, UnixListener>> for PeerAddr {
+    fn connect_info(_stream: IncomingStream<'_, UnixListener>) -> Self {
+        Self(None)
+    }
+}
+
+/// The socket passed via systemd socket activation, if any.
+fn activated_fd() -> anyhow::Result<Option<RawFd>> {
+    let Ok(pid) = env::var("LISTEN_PID") else {
+        return Ok(None);
+    };
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        // The variables were meant for a different process.
+        return Ok(None);
+    }
+
+    let fds = env::var("LISTEN_FDS").context("LISTEN_PID is set but LISTEN_FDS isn't")?;
+    match fds.parse::<u32>() {
+        Ok(0) => Ok(None),
+        Ok(1) => Ok(Some(LISTEN_FDS_START)),
+        Ok(n) => bail!("expected a single socket from systemd, got {n}"),
+        Err(_) => bail!("invalid LISTEN_FDS {fds:?}"),
+    }
+}
+
+fn from_fd(fd: RawFd) -> io::Result<Listener> {
+    // systemd doesn't set close-on-exec, so the sandbox's child processes
+    // would inherit the socket.
+    // SAFETY: Only changes the flags of a file descriptor we own.
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: systemd passes us ownership of the socket, and nothing else in
+    // this process uses the file descriptor.
+    let tcp = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    if tcp.local_addr().is_ok() {
+        tcp.set_nonblocking(true)?;
+        return Ok(Listener::Tcp(TcpListener::from_std(tcp)?));
+    }
+
+    // The socket has no IP address, so it must be a Unix domain socket.
+    // SAFETY: See above, the TCP listener gives up ownership again.
+    let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+    unix.set_nonblocking(true)?;
+    Ok(Listener::Unix(UnixListener::from_std(unix)?))
+}
+
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    // A socket left over from a previous run would make binding fail.
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            fs::remove_file(path)?;
+        }
+    }
+    UnixListener::bind(path)
+}
+
+/// Use the socket passed by systemd if there is one, otherwise bind to `addr`.
+pub async fn bind(addr: Option<&str>) -> anyhow::Result<Listener> {
+    if let Some(fd) = activated_fd()? {
+        return from_fd(fd).context("failed to use socket passed by systemd");
+    }
+
+    let Some(addr) = addr else {
+        bail!("no address to listen on");
+    };
+
+    if let Some(path) = addr.strip_prefix(UNIX_PREFIX) {
+        let listener = bind_unix(Path::new(path))
+            .with_context(|| format!("failed to bind to unix socket {path}"))?;
+        Ok(Listener::Unix(listener))
+    } else {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind to {addr}"))?;
+        Ok(Listener::Tcp(listener))
+    }
+}
+
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!(signal = "SIGINT", "shutting down"),
+        _ = terminate.recv() => info!(signal = "SIGTERM", "shutting down"),
+    }
+}
+
+async fn run<L>(listener: L, app: Router, deadline: Duration) -> anyhow::Result<()>
+where
+    L: axum::serve::Listener,
+    L::Addr: Debug,
+    PeerAddr: for<'a> Connected<IncomingStream<'a, L>>,
+{
+    let shutdown = Arc::new(Notify::new());
+    let app = app.into_make_service_with_connect_info::<PeerAddr>();
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown({
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown_signal().await;
+                shutdown.notify_one();
+            }
+        })
+        .into_future();
+
+    tokio::select! {
+        result = server => result?,
+        () = async {
+            shutdown.notified().await;
+            tokio::time::sleep(deadline).await;
+        } => {
+            // Renders on the blocking pool can't be cancelled, and the runtime
+            // would wait for them to finish when shutting down.
+            warn!(?deadline, "requests still running after shutdown deadline, exiting");
+            std::process::exit(1);
+        }
+    }
+
+    info!("all requests finished");
+    Ok(())
+}
+
+/// Serve until SIGTERM or SIGINT, then stop accepting connections and give
+/// running requests until the deadline to finish.
+pub async fn serve(listener: Listener, app: Router, deadline: Duration) -> anyhow::Result<()> {
+    match listener {
+        Listener::Tcp(listener) => {
+            info!(addr = %listener.local_addr()?, "listening");
+            run(listener, app, deadline).await
+        }
+        Listener::Unix(listener) => {
+            info!(addr = ?listener.local_addr()?, "listening");
+            run(listener, app, deadline).await
+        }
+    }
+}
@@ -1,24 +1,400 @@
-mod endpoints;
-mod render;
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+    time::Duration,
+};
 
-use axum::{routing::get, Router};
-use clap::Parser;
-use tokio::net::TcpListener;
+use anyhow::anyhow;
+use axum::{body::Body, extract::MatchedPath, http::Request};
+use clap::{Parser, Subcommand};
+use jiff::tz::TimeZone;
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{info_span, Level, Span};
+use tracing_subscriber::EnvFilter;
+
+use arbeitszeitdokumentationsgenerator::{
+    config::{Config, LogFormat},
+    endpoints::{
+        self,
+        compile::CompileJson,
+        i18n::Lang,
+        tsg::{GlobalJson, MonthJson},
+        AppState,
+    },
+    listen,
+    render::{
+        self, Format, Language, Templates, Timesheet, DEFAULT_HOLIDAY_STATE, DEFAULT_TIME_ZONE,
+    },
+};
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    addr: String,
+    /// Address to serve the web UI on, or `unix:<path>` for a Unix domain socket.
+    /// Ignored when a socket is passed via systemd socket activation.
+    #[arg(env = "ABZDOKGEN_ADDR")]
+    addr: Option<String>,
+    /// Path prefix under which all endpoints are served, e.g. `/abzdokgen`.
+    #[arg(long, env = "ABZDOKGEN_BASE_PATH")]
+    base_path: Option<String>,
+    /// Honor the `X-Forwarded-Prefix`, `X-Forwarded-Proto` and `X-Forwarded-For`
+    /// headers. Only use this if the server is exclusively reachable through a
+    /// reverse proxy that sets or removes them.
+    #[arg(long, env = "ABZDOKGEN_TRUST_FORWARDED_HEADERS")]
+    trust_forwarded_headers: bool,
+    /// Path to a TOML config file. Flags override values from the file.
+    #[arg(long, env = "ABZDOKGEN_CONFIG")]
+    config: Option<PathBuf>,
+    /// German state whose public holidays are used during validation.
+    #[arg(long, env = "ABZDOKGEN_HOLIDAY_STATE")]
+    holiday_state: Option<String>,
+    /// Time zone used for today's date, e.g. `Europe/Berlin`.
+    #[arg(long, env = "ABZDOKGEN_TIME_ZONE")]
+    time_zone: Option<String>,
+    /// Directory with additional templates, one per subdirectory. Changes are
+    /// picked up while the server is running.
+    #[arg(long, env = "ABZDOKGEN_TEMPLATES_DIR")]
+    templates_dir: Option<PathBuf>,
+    /// Log filter, e.g. `info` or `arbeitszeitdokumentationsgenerator=debug`.
+    #[arg(long, env = "ABZDOKGEN_LOG_LEVEL")]
+    log_level: Option<String>,
+    /// Log output format.
+    #[arg(long, env = "ABZDOKGEN_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+    /// Maximum number of documents generated at the same time.
+    #[arg(long, env = "ABZDOKGEN_MAX_RENDERS")]
+    max_renders: Option<usize>,
+    /// Maximum number of requests waiting for a document to be generated.
+    #[arg(long, env = "ABZDOKGEN_MAX_QUEUE")]
+    max_queue: Option<usize>,
+    /// Maximum time in seconds generating a single document may take.
+    #[arg(long, env = "ABZDOKGEN_RENDER_TIMEOUT")]
+    render_timeout: Option<u64>,
+    /// Maximum time in seconds compiling a self-written typst file may take.
+    #[arg(long, env = "ABZDOKGEN_SANDBOX_TIMEOUT")]
+    sandbox_timeout: Option<u64>,
+    /// Maximum memory in MiB compiling a self-written typst file may use.
+    #[arg(long, env = "ABZDOKGEN_SANDBOX_MAX_MEMORY")]
+    sandbox_max_memory: Option<u64>,
+    /// Maximum time in seconds to wait for running requests when shutting down.
+    #[arg(long, env = "ABZDOKGEN_SHUTDOWN_TIMEOUT")]
+    shutdown_timeout: Option<u64>,
+    /// SQLite database submitted time sheets are stored in.
+    #[arg(long, env = "ABZDOKGEN_REVIEW_DATABASE")]
+    review_database: Option<PathBuf>,
+    /// Password of the certificate approved time sheets are signed with.
+    #[arg(
+        long,
+        env = "ABZDOKGEN_REVIEW_CERTIFICATE_PASSWORD",
+        hide_env_values = true
+    )]
+    review_certificate_password: Option<String>,
+    /// SQLite database saved profiles are stored in.
+    #[arg(long, env = "ABZDOKGEN_PROFILES_DATABASE")]
+    profiles_database: Option<PathBuf>,
+    /// Password for the SMTP server.
+    #[arg(long, env = "ABZDOKGEN_SMTP_PASSWORD", hide_env_values = true)]
+    smtp_password: Option<String>,
+    /// Maximum size of a request body in bytes.
+    #[arg(long, env = "ABZDOKGEN_MAX_BODY_SIZE")]
+    max_body_size: Option<usize>,
+    /// Maximum number of entries in a single time sheet.
+    #[arg(long, env = "ABZDOKGEN_MAX_ENTRIES")]
+    max_entries: Option<usize>,
+    /// Maximum number of characters in a single text field.
+    #[arg(long, env = "ABZDOKGEN_MAX_FIELD_LENGTH")]
+    max_field_length: Option<usize>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Args {
+    fn load_config(self) -> anyhow::Result<Config> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+
+        if let Some(addr) = self.addr {
+            config.addr = Some(addr);
+        }
+        if let Some(base_path) = self.base_path {
+            config.base_path = base_path;
+        }
+        if self.trust_forwarded_headers {
+            config.trust_forwarded_headers = true;
+        }
+        if let Some(holiday_state) = self.holiday_state {
+            config.holiday_state = holiday_state;
+        }
+        if let Some(time_zone) = self.time_zone {
+            config.time_zone = time_zone;
+        }
+        if let Some(templates_dir) = self.templates_dir {
+            config.templates_dir = Some(templates_dir);
+        }
+        if let Some(log_level) = self.log_level {
+            config.logging.level = log_level;
+        }
+        if let Some(log_format) = self.log_format {
+            config.logging.format = log_format;
+        }
+        if let Some(max_renders) = self.max_renders {
+            config.render.max_renders = max_renders;
+        }
+        if let Some(max_queue) = self.max_queue {
+            config.render.max_queue = max_queue;
+        }
+        if let Some(render_timeout) = self.render_timeout {
+            config.render.timeout = render_timeout;
+        }
+        if let Some(sandbox_timeout) = self.sandbox_timeout {
+            config.sandbox.timeout = sandbox_timeout;
+        }
+        if let Some(sandbox_max_memory) = self.sandbox_max_memory {
+            config.sandbox.max_memory_mib = sandbox_max_memory;
+        }
+        if let Some(shutdown_timeout) = self.shutdown_timeout {
+            config.render.shutdown_timeout = shutdown_timeout;
+        }
+        if let Some(review_database) = self.review_database {
+            config.review.database = review_database;
+        }
+        if let Some(password) = self.review_certificate_password {
+            config.review.certificate_password = password;
+        }
+        if let Some(profiles_database) = self.profiles_database {
+            config.profiles.database = profiles_database;
+        }
+        if let (Some(password), Some(smtp)) = (self.smtp_password, &mut config.smtp) {
+            smtp.password = Some(password);
+        }
+        if let Some(max_body_size) = self.max_body_size {
+            config.limits.max_body_bytes = max_body_size;
+        }
+        if let Some(max_entries) = self.max_entries {
+            config.limits.max_entries = max_entries;
+        }
+        if let Some(max_field_length) = self.max_field_length {
+            config.limits.max_field_chars = max_field_length;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+// Parsed once on startup, so the size of the variants doesn't matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum Command {
+    /// Render TimeSheetGenerator-compatible JSON files without starting a server.
+    Render {
+        /// Path to the Global.json file.
+        global: PathBuf,
+        /// Path to the Month.json file.
+        month: PathBuf,
+        /// Where to write the rendered document.
+        output: PathBuf,
+        /// Template to fill in.
+        #[arg(long, default_value = "kit")]
+        template: String,
+        /// Directory with additional templates, one per subdirectory.
+        #[arg(long)]
+        templates_dir: Option<PathBuf>,
+        /// Output format (pdf, png or svg).
+        #[arg(long, default_value = "pdf")]
+        format: String,
+        /// Resolution of png output.
+        #[arg(long)]
+        dpi: Option<f32>,
+        /// PDF standard to conform to (a-2b or a-3b).
+        #[arg(long)]
+        standard: Option<String>,
+        /// Keep entries in their original order.
+        #[arg(long)]
+        no_sort: bool,
+        /// Skip validating the entries.
+        #[arg(long)]
+        no_validate: bool,
+        /// German state whose public holidays are used during validation.
+        #[arg(long, default_value = DEFAULT_HOLIDAY_STATE)]
+        holiday_state: String,
+        /// Time zone used for today's date.
+        #[arg(long, default_value = DEFAULT_TIME_ZONE)]
+        time_zone: String,
+        /// Language of the document (de, en or de-en for both).
+        #[arg(long, default_value = "de")]
+        language: String,
+        /// Fixed date (YYYY-MM-DD) to use as today, for reproducible output.
+        #[arg(long)]
+        today: Option<String>,
+    },
+    /// Compile a self-written main file read from stdin and print the result as
+    /// JSON. Used by the server to compile untrusted files in a child process.
+    #[command(hide = true)]
+    Compile {
+        #[arg(long)]
+        template: String,
+        #[arg(long)]
+        templates_dir: Option<PathBuf>,
+        #[arg(long)]
+        time_zone: String,
+    },
+}
+
+fn read_timesheet(
+    global: PathBuf,
+    month: PathBuf,
+    sort: bool,
+    validate: bool,
+    holiday_state: String,
+    language: Language,
+) -> anyhow::Result<Timesheet> {
+    let global = serde_json::from_str::<GlobalJson>(&fs::read_to_string(global)?)?;
+    let month = serde_json::from_str::<MonthJson>(&fs::read_to_string(month)?)?;
+    // The CLI isn't translated.
+    let messages = Lang::En.messages();
+    endpoints::tsg::timesheet(
+        global,
+        month,
+        sort,
+        validate,
+        holiday_state,
+        language,
+        messages,
+    )
+    .map_err(|e| anyhow!(e))
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+fn init_logging(config: &Config) {
+    // Validated when loading the config
+    let filter = EnvFilter::try_new(&config.logging.level).unwrap();
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.logging.format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+fn request_span(request: &Request<Body>) -> Span {
+    let id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("");
+    // Paths can contain secrets like review tokens, so log the route instead.
+    let path = (request.extensions().get::<MatchedPath>())
+        .map_or(request.uri().path(), MatchedPath::as_str);
+    info_span!("request", id, method = %request.method(), path)
+}
+
+async fn serve(config: Config) -> anyhow::Result<()> {
+    init_logging(&config);
+
+    let listener = listen::bind(config.addr.as_deref()).await?;
+    let shutdown_timeout = Duration::from_secs(config.render.shutdown_timeout);
+
+    let state = AppState::new(config)?;
+    tokio::spawn(state.templates.clone().watch());
+    if let Some(profiles) = &state.profiles {
+        tokio::spawn(profiles.clone().expire());
+    }
+
+    let app = endpoints::router(state).layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(request_span)
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .layer(PropagateRequestIdLayer::x_request_id()),
+    );
+
+    listen::serve(listener, app, shutdown_timeout).await
+}
 
-    let app = Router::<()>::new()
-        .route("/", get(endpoints::index::get).post(endpoints::index::post))
-        .route("/tsg/", get(endpoints::tsg::get).post(endpoints::tsg::post));
-    let listener = TcpListener::bind(args.addr).await?;
-    axum::serve(listener, app).await?;
+fn main() -> anyhow::Result<()> {
+    let mut args = Args::parse();
 
-    Ok(())
+    match args.command.take() {
+        Some(Command::Render {
+            global,
+            month,
+            output,
+            template,
+            templates_dir,
+            format,
+            dpi,
+            standard,
+            no_sort,
+            no_validate,
+            holiday_state,
+            time_zone,
+            language,
+            today,
+        }) => {
+            let format =
+                Format::parse(&format, dpi, standard.as_deref()).map_err(|e| anyhow!(e))?;
+            let now = render::now(TimeZone::get(&time_zone)?, today.as_deref())
+                .map_err(|e| anyhow!(e))?;
+            let language = Language::parse(&language).map_err(|e| anyhow!(e))?;
+            let timesheet = read_timesheet(
+                global,
+                month,
+                !no_sort,
+                !no_validate,
+                holiday_state,
+                language,
+            )?;
+            let templates = match templates_dir {
+                Some(dir) => Templates::with_dir(dir)?,
+                None => Templates::builtin(),
+            };
+            // The CLI isn't translated.
+            let messages = Lang::En.messages();
+            let template = templates
+                .get(Some(&template), messages)
+                .and_then(|template| template.check(&timesheet, messages).map(|()| template))
+                .map_err(|e| anyhow!(e))?;
+            let data = render::render(&*template, timesheet, format, now)
+                .map_err(|es| anyhow!(es.join("\n")))?;
+            fs::write(output, data)?;
+            Ok(())
+        }
+        Some(Command::Compile {
+            template,
+            templates_dir,
+            time_zone,
+        }) => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            let templates = match templates_dir {
+                Some(dir) => Templates::with_dir(dir)?,
+                None => Templates::builtin(),
+            };
+            // The CLI isn't translated.
+            let messages = Lang::En.messages();
+            let template = (templates.get(Some(&template), messages)).map_err(|e| anyhow!(e))?;
+            let now = render::now(TimeZone::get(&time_zone)?, None).map_err(|e| anyhow!(e))?;
+            let compiled = render::compile_source(&*template, source, now);
+            serde_json::to_writer(io::stdout().lock(), &CompileJson::from(compiled))?;
+            Ok(())
+        }
+        None => {
+            let config = args.load_config()?;
+            // Only built for the server, so the sandbox's child processes
+            // don't start a thread per core under their memory limit.
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()?
+                .block_on(serve(config))
+        }
+    }
 }
@@ -0,0 +1,109 @@
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+use crate::render::Timings;
+
+pub struct Metrics {
+    registry: Registry,
+    pub render_queue: IntGauge,
+    pub renders_active: IntGauge,
+    pub requests_active: IntGauge,
+    renders: IntCounterVec,
+    font_load_seconds: Histogram,
+    compile_seconds: Histogram,
+    export_seconds: HistogramVec,
+}
+
+/// Counts something as active until dropped, even if the request is cancelled
+/// or the render panics.
+pub struct Active(IntGauge);
+
+impl Active {
+    pub fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("abzdokgen".to_string()), None)?;
+
+        let render_queue = IntGauge::new(
+            "render_queue_depth",
+            "Number of renders waiting for a free slot",
+        )?;
+        registry.register(Box::new(render_queue.clone()))?;
+
+        let renders_active = IntGauge::new("renders_active", "Number of renders in progress")?;
+        registry.register(Box::new(renders_active.clone()))?;
+
+        let requests_active = IntGauge::new("requests_active", "Number of requests being handled")?;
+        registry.register(Box::new(requests_active.clone()))?;
+
+        let renders = IntCounterVec::new(
+            Opts::new(
+                "renders_total",
+                "Number of render requests by endpoint and outcome",
+            ),
+            &["endpoint", "outcome"],
+        )?;
+        registry.register(Box::new(renders.clone()))?;
+
+        let font_load_seconds = Histogram::with_opts(HistogramOpts::new(
+            "font_load_seconds",
+            "Time spent loading system fonts",
+        ))?;
+        registry.register(Box::new(font_load_seconds.clone()))?;
+
+        let compile_seconds = Histogram::with_opts(HistogramOpts::new(
+            "render_compile_seconds",
+            "Time spent compiling the typst document",
+        ))?;
+        registry.register(Box::new(compile_seconds.clone()))?;
+
+        let export_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "render_export_seconds",
+                "Time spent exporting the compiled document",
+            ),
+            &["format"],
+        )?;
+        registry.register(Box::new(export_seconds.clone()))?;
+
+        Ok(Self {
+            registry,
+            render_queue,
+            renders_active,
+            requests_active,
+            renders,
+            font_load_seconds,
+            compile_seconds,
+            export_seconds,
+        })
+    }
+
+    /// The outcome is either `success` or the category of the error.
+    pub fn count_render(&self, endpoint: &str, outcome: &str) {
+        self.renders.with_label_values(&[endpoint, outcome]).inc();
+    }
+
+    pub fn observe_render(&self, format: &str, timings: &Timings) {
+        self.font_load_seconds.observe(timings.fonts.as_secs_f64());
+        self.compile_seconds.observe(timings.compile.as_secs_f64());
+        self.export_seconds
+            .with_label_values(&[format])
+            .observe(timings.export.as_secs_f64());
+    }
+
+    pub fn encode(&self) -> prometheus::Result<String> {
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::Semaphore;
+
+use crate::metrics::{Active, Metrics};
+
+pub enum PoolError {
+    /// The queue is full.
+    Busy,
+    /// The render took longer than the configured timeout.
+    Timeout,
+    /// The render panicked.
+    Panicked,
+}
+
+impl PoolError {
+    /// Short name for logs and metrics.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Busy => "busy",
+            Self::Timeout => "timeout",
+            Self::Panicked => "panic",
+        }
+    }
+}
+
+/// Runs CPU-heavy renders on tokio's blocking thread pool, limiting how many
+/// can run and wait at the same time.
+pub struct RenderPool {
+    permits: Arc<Semaphore>,
+    queued: AtomicUsize,
+    max_queued: usize,
+    timeout: Duration,
+    metrics: Arc<Metrics>,
+}
+
+/// Removes a render from the queue when dropped, even if the request is
+/// cancelled while waiting.
+struct Queued<'a>(&'a RenderPool);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        let queued = self.0.queued.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.0.metrics.render_queue.set(queued as i64);
+    }
+}
+
+impl RenderPool {
+    pub fn new(
+        max_active: usize,
+        max_queued: usize,
+        timeout: Duration,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_active)),
+            queued: AtomicUsize::new(0),
+            max_queued,
+            timeout,
+            metrics,
+        }
+    }
+
+    fn enqueue(&self) -> Result<Queued<'_>, PoolError> {
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        let guard = Queued(self);
+        if queued > self.max_queued {
+            return Err(PoolError::Busy);
+        }
+        self.metrics.render_queue.set(queued as i64);
+        Ok(guard)
+    }
+
+    pub async fn run<F, T>(&self, f: F) -> Result<T, PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let queued = self.enqueue()?;
+        // The semaphore is never closed.
+        let permit = self.permits.clone().acquire_owned().await.unwrap();
+        drop(queued);
+
+        // The permit is only released once the render actually finishes. Renders
+        // that time out can't be cancelled and still occupy their slot.
+        let active = self.metrics.renders_active.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _active = Active::new(active);
+            f()
+        });
+
+        match tokio::time::timeout(self.timeout, task).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(_)) => Err(PoolError::Panicked),
+            Err(_) => Err(PoolError::Timeout),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::{path::Path, time::Duration};
+
+use jiff::Timestamp;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{db::Database, render::WorkingArea};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS profiles (
+    token      TEXT NOT NULL PRIMARY KEY,
+    profile    TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+) STRICT;
+";
+
+/// Header data that stays the same from month to month.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub staff_id: String,
+    pub department: String,
+    pub working_area: WorkingArea,
+    pub monthly_hours: u32,
+    pub hourly_wage: String,
+}
+
+/// Everything stored about a profile, as handed out when exporting it.
+#[derive(Serialize)]
+pub struct StoredProfile {
+    #[serde(flatten)]
+    pub profile: Profile,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl StoredProfile {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let profile = row.get::<_, String>(0)?;
+        let profile = serde_json::from_str::<Profile>(&profile)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, e.into()))?;
+        Ok(Self {
+            profile,
+            created_at: row.get(1)?,
+            updated_at: row.get(2)?,
+        })
+    }
+}
+
+/// Saved profiles, stored in a SQLite database and identified by a random
+/// token only their owner knows.
+pub struct Profiles {
+    db: Database,
+}
+
+impl Profiles {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::open(path, SCHEMA)?;
+        Ok(Self { db })
+    }
+
+    /// Store a new profile, returning its token.
+    pub async fn create(&self, profile: &Profile) -> rusqlite::Result<String> {
+        let token = Uuid::new_v4().simple().to_string();
+        // Serializing plain data to JSON can't fail.
+        let json = serde_json::to_string(profile).unwrap();
+        let now = Timestamp::now().to_string();
+
+        let stored = token.clone();
+        self.db
+            .run(move |db| {
+                db.execute(
+                    "INSERT INTO profiles (token, profile, created_at, updated_at) \
+                     VALUES (?1, ?2, ?3, ?3)",
+                    params![stored, json, now],
+                )
+            })
+            .await?;
+
+        Ok(token)
+    }
+
+    pub async fn get(&self, token: &str) -> rusqlite::Result<Option<StoredProfile>> {
+        let token = token.to_string();
+        self.db
+            .run(move |db| {
+                db.query_row(
+                    "SELECT profile, created_at, updated_at FROM profiles WHERE token = ?1",
+                    [token],
+                    StoredProfile::from_row,
+                )
+                .optional()
+            })
+            .await
+    }
+
+    /// Returns `false` if there is no profile with this token.
+    pub async fn update(&self, token: &str, profile: &Profile) -> rusqlite::Result<bool> {
+        let token = token.to_string();
+        // Serializing plain data to JSON can't fail.
+        let json = serde_json::to_string(profile).unwrap();
+        let changed = self
+            .db
+            .run(move |db| {
+                db.execute(
+                    "UPDATE profiles SET profile = ?2, updated_at = ?3 WHERE token = ?1",
+                    params![token, json, Timestamp::now().to_string()],
+                )
+            })
+            .await?;
+        Ok(changed > 0)
+    }
+
+    /// Returns `false` if there is no profile with this token.
+    pub async fn delete(&self, token: &str) -> rusqlite::Result<bool> {
+        let token = token.to_string();
+        let changed = self
+            .db
+            .run(move |db| db.execute("DELETE FROM profiles WHERE token = ?1", [token]))
+            .await?;
+        Ok(changed > 0)
+    }
+
+    /// Delete the profiles that weren't saved within `max_age`, returning how
+    /// many there were.
+    pub async fn delete_expired(&self, max_age: Duration) -> rusqlite::Result<usize> {
+        let max_age = max_age.as_secs() as i64;
+        self.db
+            .run(move |db| {
+                db.execute(
+                    "DELETE FROM profiles WHERE unixepoch(updated_at) < unixepoch() - ?1",
+                    [max_age],
+                )
+            })
+            .await
+    }
+}
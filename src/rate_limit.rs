@@ -0,0 +1,40 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Allows a number of events per key and fixed time window, e.g. emails per
+/// recipient and hour. Only kept in memory, so restarting resets it.
+pub struct RateLimiter {
+    max: u32,
+    window: Duration,
+    /// When each key's current window started, and how many events it had.
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(max: u32, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count an event, returning `false` if the key already used up its limit
+    /// for the current window.
+    pub fn check(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        // Forget ended windows so keys don't pile up.
+        windows.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+
+        let (_, count) = windows.entry(key.to_string()).or_insert((now, 0));
+        if *count >= self.max {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
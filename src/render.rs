@@ -1,32 +1,66 @@
-use std::{fs, path::PathBuf, sync::OnceLock};
+use std::{
+    fmt, fs,
+    path::PathBuf,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use jiff::{
+    civil,
+    tz::{Offset, TimeZone},
+    Zoned,
+};
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
 use typst::{
-    diag::{FileError, FileResult, SourceResult},
-    foundations::{Bytes, Datetime},
+    diag::{FileError, FileResult, Severity, SourceDiagnostic, SourceResult},
+    foundations::{Bytes, Datetime, Smart},
+    layout::{Abs, PagedDocument},
     syntax::{FileId, Source},
     text::{Font, FontBook, FontInfo},
     utils::LazyHash,
     Library, World,
 };
-use typst_pdf::PdfOptions;
+use typst_pdf::{PdfOptions, PdfStandard, PdfStandards};
+
+use crate::endpoints::i18n::{fill, Lang, Messages};
+
+pub use self::template::{Template, Templates};
+
+mod template;
+
+const SIGNATURE_NAME: &str = "signature";
+const SUPERVISOR_SIGNATURE_NAME: &str = "supervisor_signature";
+/// Leaves a bit of room above the signature line in the footer.
+const SIGNATURE_HEIGHT: &str = "10mm";
+
+const TITLE: &str = "Arbeitszeitdokumentation";
 
-const LOGO: &str = include_str!("../kit_logo.svg");
-const LOGO_NAME: &str = "kit_logo.svg";
+const ATTACHMENT_NAME: &str = "timesheet.json";
 
-const TEMPLATE: &str = include_str!("../kit_timesheet.typ");
-const TEMPLATE_NAME: &str = "kit_timesheet.typ";
+/// The states supported by the template's `state` parameter.
+pub const HOLIDAY_STATES: [&str; 16] = [
+    "BW", "BY", "BE", "BB", "HB", "HH", "HE", "MV", "NI", "NW", "RP", "SL", "SN", "ST", "SH", "TH",
+];
+pub const DEFAULT_HOLIDAY_STATE: &str = "BW";
 
-const ALIAS: &str = "ts";
+pub const DEFAULT_TIME_ZONE: &str = "Europe/Berlin";
+
+pub const DEFAULT_DPI: f32 = 144.0;
+pub const MAX_DPI: f32 = 600.0;
 
 //////////
 // Data //
 //////////
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum WorkingArea {
     Großforschung,
     Unibereich,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Note {
     Urlaub,
     Krankheit,
@@ -34,6 +68,79 @@ pub enum Note {
     Sonstiges,
 }
 
+/// Language of the document's labels and of validation messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "en")]
+    English,
+    /// German labels with English translations.
+    #[serde(rename = "de-en")]
+    Bilingual,
+}
+
+impl Language {
+    pub fn parse(name: &str) -> Result<Self, InputError> {
+        match name {
+            "de" => Ok(Self::German),
+            "en" => Ok(Self::English),
+            "de-en" => Ok(Self::Bilingual),
+            _ => Err(InputError::UnknownLanguage(name.to_string())),
+        }
+    }
+}
+
+/// An invalid option or value passed along with a time sheet.
+#[derive(Debug)]
+pub enum InputError {
+    UnknownLanguage(String),
+    InvalidStandard(String),
+    /// Only PDFs can conform to a standard.
+    StandardNotSupported(String),
+    InvalidFormat(String),
+    InvalidDpi(f32),
+    InvalidSignatureImage,
+    UnsupportedSignatureImage(String),
+    InvalidDate(String),
+}
+
+impl InputError {
+    pub fn message(&self, m: &Messages) -> String {
+        match self {
+            Self::UnknownLanguage(name) => {
+                fill(m.unknown_language, &[("value", &format!("{name:?}"))])
+            }
+            Self::InvalidStandard(name) => {
+                fill(m.invalid_standard, &[("value", &format!("{name:?}"))])
+            }
+            Self::StandardNotSupported(format) => fill(
+                m.standard_not_supported,
+                &[("value", &format!("{format:?}"))],
+            ),
+            Self::InvalidFormat(name) => fill(m.invalid_format, &[("value", &format!("{name:?}"))]),
+            Self::InvalidDpi(dpi) => fill(m.invalid_dpi, &[("value", dpi), ("max", &MAX_DPI)]),
+            Self::InvalidSignatureImage => m.invalid_signature_image.to_string(),
+            Self::UnsupportedSignatureImage(media_type) => fill(
+                m.unsupported_signature_image,
+                &[("value", &format!("{media_type:?}"))],
+            ),
+            Self::InvalidDate(date) => fill(m.invalid_date, &[("value", &format!("{date:?}"))]),
+        }
+    }
+}
+
+/// In English, for the CLI and logs.
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message(Lang::En.messages()))
+    }
+}
+
+impl std::error::Error for InputError {}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub task: String,
     pub day: u32,
@@ -43,6 +150,159 @@ pub struct Entry {
     pub note: Option<Note>,
 }
 
+#[derive(Clone, Copy)]
+pub enum Standard {
+    /// PDF/A-2b, for long-term archiving.
+    A2b,
+    /// PDF/A-3b, like PDF/A-2b but allows arbitrary embedded files.
+    A3b,
+}
+
+impl Standard {
+    pub fn parse(name: &str) -> Result<Self, InputError> {
+        match name {
+            "a-2b" => Ok(Self::A2b),
+            "a-3b" => Ok(Self::A3b),
+            _ => Err(InputError::InvalidStandard(name.to_string())),
+        }
+    }
+
+    fn pdf_standard(self) -> PdfStandard {
+        match self {
+            Self::A2b => PdfStandard::A_2b,
+            Self::A3b => PdfStandard::A_3b,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Pdf { standard: Option<Standard> },
+    Png { dpi: f32 },
+    Svg,
+}
+
+impl Format {
+    pub fn parse(name: &str, dpi: Option<f32>, standard: Option<&str>) -> Result<Self, InputError> {
+        let standard = standard.map(Standard::parse).transpose()?;
+        if standard.is_some() && name != "pdf" {
+            return Err(InputError::StandardNotSupported(name.to_string()));
+        }
+
+        let format = match name {
+            "pdf" => Self::Pdf { standard },
+            "png" => Self::Png {
+                dpi: dpi.unwrap_or(DEFAULT_DPI),
+            },
+            "svg" => Self::Svg,
+            _ => return Err(InputError::InvalidFormat(name.to_string())),
+        };
+
+        if let Self::Png { dpi } = format {
+            if !(dpi > 0.0 && dpi <= MAX_DPI) {
+                return Err(InputError::InvalidDpi(dpi));
+            }
+        }
+
+        Ok(format)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pdf { .. } => "pdf",
+            Self::Png { .. } => "png",
+            Self::Svg => "svg",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Pdf { .. } => "application/pdf",
+            Self::Png { .. } => "image/png",
+            Self::Svg => "image/svg+xml",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+/// Split a base64 `data:` URL as produced by `FileReader.readAsDataURL` into
+/// its media type and data.
+pub fn parse_data_url(url: &str) -> Option<(&str, Vec<u8>)> {
+    let (media_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+    let data = BASE64.decode(data).ok()?;
+    Some((media_type, data))
+}
+
+/// An image of a handwritten signature.
+#[derive(Clone)]
+pub struct Signature {
+    pub format: ImageFormat,
+    pub data: Vec<u8>,
+}
+
+impl Signature {
+    pub fn parse_data_url(url: &str) -> Result<Self, InputError> {
+        let (media_type, data) = parse_data_url(url).ok_or(InputError::InvalidSignatureImage)?;
+        let format = match media_type {
+            "image/png" => ImageFormat::Png,
+            "image/svg+xml" => ImageFormat::Svg,
+            _ => {
+                return Err(InputError::UnsupportedSignatureImage(
+                    media_type.to_string(),
+                ))
+            }
+        };
+        Ok(Self { format, data })
+    }
+
+    pub fn to_data_url(&self) -> String {
+        let media_type = match self.format {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Svg => "image/svg+xml",
+        };
+        format!("data:{media_type};base64,{}", BASE64.encode(&self.data))
+    }
+
+    fn file_name(&self, name: &str) -> String {
+        match self.format {
+            ImageFormat::Png => format!("{name}.png"),
+            ImageFormat::Svg => format!("{name}.svg"),
+        }
+    }
+}
+
+/// The time typst sees as the present. Usually the current time in the given
+/// time zone, but it can be fixed to a date (`YYYY-MM-DD`) for reproducible
+/// output. Noon is used so that the date stays the same for most UTC offsets
+/// typst might ask for.
+pub fn now(time_zone: TimeZone, today: Option<&str>) -> Result<Zoned, InputError> {
+    let Some(today) = today else {
+        return Ok(Zoned::now().with_time_zone(time_zone));
+    };
+
+    let invalid = || InputError::InvalidDate(today.to_string());
+    let date = today.parse::<civil::Date>().map_err(|_| invalid())?;
+    // Only fails for dates at the very edge of the supported range.
+    date.at(12, 0, 0, 0)
+        .to_zoned(time_zone)
+        .map_err(|_| invalid())
+}
+
+/// The supervisor's confirmation that they checked the time sheet.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Approval {
+    pub name: String,
+    pub date: civil::Date,
+    #[serde(skip)]
+    pub signature: Option<Signature>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Timesheet {
     pub name: String,
     pub staff_id: String,
@@ -55,9 +315,30 @@ pub struct Timesheet {
     pub carry_prev_month: Option<String>,
     pub year: u32,
     pub month: u32,
+    /// German state whose public holidays are used during validation.
+    #[serde(default = "default_holiday_state")]
+    pub holiday_state: String,
+    /// Pre-fill the date next to the employee's signature with today's date.
+    #[serde(default)]
+    pub date_signature: bool,
+    /// Date next to the employee's signature. Takes precedence over
+    /// `date_signature`.
+    #[serde(default)]
+    pub signature_date: Option<civil::Date>,
+    /// Not part of the embedded data, it's visible in the document anyway.
+    #[serde(skip)]
+    pub signature: Option<Signature>,
+    #[serde(default)]
+    pub approval: Option<Approval>,
+    #[serde(default)]
+    pub language: Language,
     pub entries: Vec<Entry>,
 }
 
+fn default_holiday_state() -> String {
+    DEFAULT_HOLIDAY_STATE.to_string()
+}
+
 ///////////////////////
 // Convert to source //
 ///////////////////////
@@ -89,65 +370,44 @@ fn fmt_bool(b: bool) -> String {
     .to_string()
 }
 
-fn fmt_area(area: WorkingArea) -> String {
-    let name = match area {
-        WorkingArea::Großforschung => "Großforschung",
-        WorkingArea::Unibereich => "Unibereich",
-    };
-    format!("{ALIAS}.areas.{name}")
+fn fmt_date(date: civil::Date) -> String {
+    format!(
+        "datetime(year: {}, month: {}, day: {})",
+        date.year(),
+        date.month(),
+        date.day()
+    )
 }
 
-fn fmt_note(note: Note) -> String {
-    let name = match note {
-        Note::Urlaub => "Urlaub",
-        Note::Krankheit => "Krankheit",
-        Note::Feiertag => "Feiertag",
-        Note::Sonstiges => "Sonstiges",
-    };
-    format!("{ALIAS}.notes.{name}")
+fn fmt_subject(ts: &Timesheet) -> String {
+    format!("{:02}/{}", ts.month, ts.year)
 }
 
-fn fmt_entry(entry: Entry) -> String {
-    let mut args = vec![
-        fmt_str(&entry.task),
-        fmt_int(entry.day),
-        fmt_str(&entry.start),
-        fmt_str(&entry.end),
+fn fmt_attachment(ts: &Timesheet) -> String {
+    // Serializing plain data to JSON can't fail.
+    let json = serde_json::to_string(ts).unwrap();
+    let args = [
+        fmt_str(ATTACHMENT_NAME),
+        format!("bytes({})", fmt_str(&json)),
+        format!("relationship: {}", fmt_str("source")),
+        format!("mime-type: {}", fmt_str("application/json")),
+        format!("description: {}", fmt_str("Timesheet data")),
     ];
-
-    if let Some(rest) = entry.rest {
-        args.push(format!("rest: {}", fmt_str(&rest)));
-    }
-
-    if let Some(note) = entry.note {
-        args.push(format!("note: {}", fmt_note(note)));
-    }
-
-    format!("{ALIAS}.entry({})", args.join(", "))
+    format!("#pdf.embed({})", args.join(", "))
 }
 
-fn fmt_timesheet(ts: Timesheet) -> String {
+fn fmt_main(template: &dyn Template, ts: &Timesheet, attach: bool) -> String {
     let mut lines = vec![];
 
-    lines.push(format!("#import {} as {ALIAS}", fmt_str(TEMPLATE_NAME)));
-    lines.push(format!("#{ALIAS}.timesheet("));
-    lines.push(format!("  name: {},", fmt_str(&ts.name)));
-    lines.push(format!("  staff_id: {},", fmt_str(&ts.staff_id)));
-    lines.push(format!("  department: {},", fmt_str(&ts.department)));
-    lines.push(format!("  working_area: {},", fmt_area(ts.working_area)));
-    lines.push(format!("  monthly_hours: {},", fmt_int(ts.monthly_hours)));
-    lines.push(format!("  hourly_wage: {},", fmt_str(&ts.hourly_wage)));
-    lines.push(format!("  validate: {},", fmt_bool(ts.validate)));
-    lines.push(format!("  sort: {},", fmt_bool(ts.sort)));
-    if let Some(carry) = ts.carry_prev_month {
-        lines.push(format!("  carry_prev_month: {},", fmt_str(&carry)));
-    }
-    lines.push(format!("  year: {},", fmt_int(ts.year)));
-    lines.push(format!("  month: {},", fmt_int(ts.month)));
-    for entry in ts.entries {
-        lines.push(format!("  {},", fmt_entry(entry)));
-    }
+    lines.push("#set document(".to_string());
+    lines.push(format!("  title: {},", fmt_str(TITLE)));
+    lines.push(format!("  author: {},", fmt_str(&ts.name)));
+    lines.push(format!("  description: {},", fmt_str(&fmt_subject(ts))));
     lines.push(")".to_string());
+    if attach {
+        lines.push(fmt_attachment(ts));
+    }
+    lines.push(template.main(ts));
     lines.join("\n")
 }
 
@@ -176,6 +436,13 @@ impl FontSlot {
     }
 }
 
+fn face_path(face: &fontdb::FaceInfo) -> Option<&PathBuf> {
+    match &face.source {
+        fontdb::Source::File(path) | fontdb::Source::SharedFile(path, _) => Some(path),
+        fontdb::Source::Binary(_) => None,
+    }
+}
+
 fn load_system_fonts() -> (FontBook, Vec<FontSlot>) {
     let mut book = FontBook::new();
     let mut fonts = vec![];
@@ -183,10 +450,14 @@ fn load_system_fonts() -> (FontBook, Vec<FontSlot>) {
     let mut db = fontdb::Database::new();
     db.load_system_fonts();
 
-    for face in db.faces() {
-        let path = match &face.source {
-            fontdb::Source::File(path) | fontdb::Source::SharedFile(path, _) => path,
-            fontdb::Source::Binary(_) => continue,
+    // The database lists fonts in whatever order the file system returns them.
+    // Sorting them ensures typst always picks the same font when several match.
+    let mut faces = db.faces().collect::<Vec<_>>();
+    faces.sort_by_key(|face| (face_path(face), face.index));
+
+    for face in faces {
+        let Some(path) = face_path(face) else {
+            continue;
         };
 
         if let Some(info) = db.with_face_data(face.id, FontInfo::new).unwrap() {
@@ -202,26 +473,39 @@ fn load_system_fonts() -> (FontBook, Vec<FontSlot>) {
     (book, fonts)
 }
 
-struct DummyWorld {
+struct DummyWorld<'a> {
     library: LazyHash<Library>,
     book: LazyHash<FontBook>,
+    template: &'a dyn Template,
     main: Source,
     fonts: Vec<FontSlot>,
+    now: Zoned,
+    /// Signature images by file name.
+    images: Vec<(String, Bytes)>,
 }
 
-impl DummyWorld {
-    fn new(main: String) -> Self {
-        let (book, fonts) = load_system_fonts();
+impl<'a> DummyWorld<'a> {
+    fn new(
+        template: &'a dyn Template,
+        main: String,
+        book: FontBook,
+        fonts: Vec<FontSlot>,
+        now: Zoned,
+        images: Vec<(String, Bytes)>,
+    ) -> Self {
         Self {
             library: LazyHash::new(Library::builder().build()),
             book: LazyHash::new(book),
+            template,
             main: Source::detached(main),
             fonts,
+            now,
+            images,
         }
     }
 }
 
-impl World for DummyWorld {
+impl World for DummyWorld<'_> {
     fn library(&self) -> &LazyHash<Library> {
         &self.library
     }
@@ -238,38 +522,493 @@ impl World for DummyWorld {
         if id == self.main.id() {
             return Ok(self.main.clone());
         }
+        if id.package().is_some() {
+            return Err(FileError::Other(Some("packages are not available".into())));
+        }
 
         let path = id.vpath().as_rootless_path();
-        match path.to_string_lossy().as_ref() {
-            TEMPLATE_NAME => Ok(Source::new(id, TEMPLATE.to_string())),
-            _ => Err(FileError::NotFound(path.to_path_buf())),
+        match self.template.source(&path.to_string_lossy()) {
+            Some(text) => Ok(Source::new(id, text)),
+            None => Err(FileError::NotFound(path.to_path_buf())),
         }
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        let path = id.vpath().as_rootless_path();
-        match path.to_string_lossy().as_ref() {
-            LOGO_NAME => Ok(Bytes::new(LOGO.as_bytes())),
-            _ => Err(FileError::NotFound(path.to_path_buf())),
+        if id.package().is_some() {
+            return Err(FileError::Other(Some("packages are not available".into())));
         }
+        let path = id.vpath().as_rootless_path();
+        let name = path.to_string_lossy();
+        (self.images.iter())
+            .find(|(image, _)| *image == name)
+            .map(|(_, data)| data.clone())
+            .or_else(|| self.template.file(&name))
+            .ok_or_else(|| FileError::NotFound(path.to_path_buf()))
     }
 
     fn font(&self, index: usize) -> Option<Font> {
         self.fonts[index].get()
     }
 
-    fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
-        panic!("datetimes are not supported")
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        // Without an explicit offset, typst expects the local date.
+        let date = match offset {
+            None => self.now.date(),
+            Some(hours) => {
+                let offset = Offset::from_hours(i8::try_from(hours).ok()?).ok()?;
+                self.now.with_time_zone(TimeZone::fixed(offset)).date()
+            }
+        };
+        Datetime::from_ymd(
+            date.year().into(),
+            date.month().try_into().ok()?,
+            date.day().try_into().ok()?,
+        )
     }
 }
 
-fn render_pdf(ts: Timesheet) -> SourceResult<Vec<u8>> {
-    let world = DummyWorld::new(fmt_timesheet(ts));
-    let document = typst::compile(&world).output?;
-    let options = PdfOptions::default();
-    typst_pdf::pdf(&document, &options)
+fn images(ts: &Timesheet) -> Vec<(String, Bytes)> {
+    let supervisor_signature = ts.approval.as_ref().and_then(|a| a.signature.as_ref());
+    [
+        (SIGNATURE_NAME, ts.signature.as_ref()),
+        (SUPERVISOR_SIGNATURE_NAME, supervisor_signature),
+    ]
+    .into_iter()
+    .filter_map(|(name, signature)| {
+        let signature = signature?;
+        Some((
+            signature.file_name(name),
+            Bytes::new(signature.data.clone()),
+        ))
+    })
+    .collect()
+}
+
+/// How long the individual steps of a render took.
+#[derive(Default)]
+pub struct Timings {
+    pub fonts: Duration,
+    pub compile: Duration,
+    pub export: Duration,
+}
+
+fn compile(
+    template: &dyn Template,
+    ts: Timesheet,
+    attach: bool,
+    now: Zoned,
+) -> SourceResult<PagedDocument> {
+    compile_timed(template, ts, attach, now, &mut Timings::default())
 }
 
-pub fn render(ts: Timesheet) -> Result<Vec<u8>, Vec<String>> {
-    render_pdf(ts).map_err(|es| es.iter().map(|e| e.message.to_string()).collect::<Vec<_>>())
+fn compile_timed(
+    template: &dyn Template,
+    ts: Timesheet,
+    attach: bool,
+    now: Zoned,
+    timings: &mut Timings,
+) -> SourceResult<PagedDocument> {
+    let start = Instant::now();
+    let (book, fonts) = load_system_fonts();
+    timings.fonts = start.elapsed();
+
+    let images = images(&ts);
+    let main = fmt_main(template, &ts, attach);
+    let world = DummyWorld::new(template, main, book, fonts, now, images);
+    let start = Instant::now();
+    let result = typst::compile(&world).output;
+    timings.compile = start.elapsed();
+    result
+}
+
+/// A stable identifier for the document, so that re-rendering the same month
+/// for the same person produces a PDF with the same document ID.
+fn pdf_ident(ts: &Timesheet) -> String {
+    format!(
+        "{TITLE} {} {} {:04}-{:02}",
+        ts.name, ts.staff_id, ts.year, ts.month
+    )
+}
+
+/// The document is dated to the time typst sees as the present, see [`now`].
+/// Fixing that to a date makes the timestamp, and with it the whole PDF,
+/// reproducible.
+fn pdf_timestamp(now: &Zoned) -> Option<typst_pdf::Timestamp> {
+    let utc = now.with_time_zone(TimeZone::UTC);
+    let datetime = Datetime::from_ymd_hms(
+        utc.year().into(),
+        utc.month().try_into().ok()?,
+        utc.day().try_into().ok()?,
+        utc.hour().try_into().ok()?,
+        utc.minute().try_into().ok()?,
+        utc.second().try_into().ok()?,
+    )?;
+    Some(typst_pdf::Timestamp::new_utc(datetime))
+}
+
+fn render_pdf(
+    document: &PagedDocument,
+    ident: &str,
+    timestamp: Option<typst_pdf::Timestamp>,
+    standard: Option<Standard>,
+) -> Result<Vec<u8>, Vec<String>> {
+    let standards = match standard {
+        Some(standard) => {
+            PdfStandards::new(&[standard.pdf_standard()]).map_err(|e| vec![e.to_string()])?
+        }
+        None => PdfStandards::default(),
+    };
+
+    let options = PdfOptions {
+        ident: Smart::Custom(ident),
+        timestamp,
+        standards,
+        ..PdfOptions::default()
+    };
+
+    typst_pdf::pdf(document, &options).map_err(|es| fmt_errors(&es))
+}
+
+fn render_png(document: &PagedDocument, dpi: f32) -> Result<Vec<u8>, String> {
+    // Typst measures everything in points, of which there are 72 per inch.
+    let pixmap = typst_render::render_merged(document, dpi / 72.0, Abs::zero(), None);
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+fn render_svg(document: &PagedDocument) -> Vec<u8> {
+    typst_svg::svg_merged(document, Abs::zero()).into_bytes()
+}
+
+fn fmt_errors(errors: &[SourceDiagnostic]) -> Vec<String> {
+    errors.iter().map(|e| e.message.to_string()).collect()
+}
+
+pub struct Problem {
+    pub message: String,
+    /// 1-based position of the offending entry in [`Timesheet::entries`].
+    pub row: Option<usize>,
+    pub day: Option<u32>,
+}
+
+impl Problem {
+    fn parse(message: String) -> Self {
+        // The template's validation messages look like "row 3 (day 5): ..." or
+        // "day 5: ...", or "Zeile 3 (Tag 5): ..." and "Tag 5: ..." in German,
+        // see _assert_entry and _assert_day in the template.
+        let text = message
+            .strip_prefix("assertion failed: ")
+            .unwrap_or(&message);
+        let row_prefix = (text.strip_prefix("row ").map(|rest| (rest, " (day ")))
+            .or_else(|| text.strip_prefix("Zeile ").map(|rest| (rest, " (Tag ")));
+        let day_prefix = (text.strip_prefix("day ")).or_else(|| text.strip_prefix("Tag "));
+
+        let (row, day) = if let Some((rest, day_infix)) = row_prefix {
+            let (row, rest) = rest.split_once(day_infix).unwrap_or((rest, ""));
+            let day = rest.split_once("):").and_then(|(day, _)| day.parse().ok());
+            (row.parse().ok(), day)
+        } else if let Some(rest) = day_prefix {
+            let day = rest.split_once(':').and_then(|(day, _)| day.parse().ok());
+            (None, day)
+        } else {
+            (None, None)
+        };
+
+        Self { message, row, day }
+    }
+}
+
+pub struct Preview {
+    pub svg: Option<String>,
+    pub problems: Vec<Problem>,
+}
+
+/// Render a timesheet even if it doesn't pass validation, collecting the
+/// validation problems alongside the image.
+pub fn preview(template: &dyn Template, ts: Timesheet, now: Zoned) -> Preview {
+    let mut errors = vec![];
+
+    if ts.validate {
+        // Validation doesn't depend on the order of the entries. Keeping them
+        // unsorted means that row numbers refer to positions in the input.
+        let checked = Timesheet {
+            sort: false,
+            ..ts.clone()
+        };
+        if let Err(es) = compile(template, checked, false, now.clone()) {
+            errors = fmt_errors(&es);
+        }
+    }
+
+    let unchecked = Timesheet {
+        validate: false,
+        ..ts
+    };
+    let svg = match compile(template, unchecked, false, now) {
+        Ok(document) => Some(typst_svg::svg_merged(&document, Abs::zero())),
+        Err(es) => {
+            if errors.is_empty() {
+                errors = fmt_errors(&es);
+            }
+            None
+        }
+    };
+
+    let problems = errors.into_iter().map(Problem::parse).collect();
+    Preview { svg, problems }
+}
+
+pub fn render(
+    template: &dyn Template,
+    ts: Timesheet,
+    format: Format,
+    now: Zoned,
+) -> Result<Vec<u8>, Vec<String>> {
+    render_timed(template, ts, format, now, &mut Timings::default())
+}
+
+pub fn render_timed(
+    template: &dyn Template,
+    ts: Timesheet,
+    format: Format,
+    now: Zoned,
+    timings: &mut Timings,
+) -> Result<Vec<u8>, Vec<String>> {
+    let ident = pdf_ident(&ts);
+    let timestamp = pdf_timestamp(&now);
+    // PDF/A-2 only allows embedding other PDF/A files, so the source data can't
+    // be attached there.
+    let attach = !matches!(
+        format,
+        Format::Pdf {
+            standard: Some(Standard::A2b)
+        }
+    );
+    let document =
+        compile_timed(template, ts, attach, now, timings).map_err(|es| fmt_errors(&es))?;
+
+    let start = Instant::now();
+    let result = match format {
+        Format::Pdf { standard } => render_pdf(&document, &ident, timestamp, standard),
+        Format::Png { dpi } => render_png(&document, dpi).map_err(|e| vec![e]),
+        Format::Svg => Ok(render_svg(&document)),
+    };
+    timings.export = start.elapsed();
+    result
+}
+
+/// A message from typst, located in the source it refers to.
+pub struct Diagnostic {
+    pub error: bool,
+    pub message: String,
+    pub hints: Vec<String>,
+    /// The template file the diagnostic refers to, or `None` for the main file.
+    pub file: Option<String>,
+    /// 1-based.
+    pub line: Option<usize>,
+    /// 1-based, in characters.
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(world: &DummyWorld<'_>, diagnostic: &SourceDiagnostic) -> Self {
+        let id = diagnostic.span.id();
+        let position = id.and_then(|id| {
+            let source = world.source(id).ok()?;
+            let start = source.range(diagnostic.span)?.start;
+            Some((source.byte_to_line(start)?, source.byte_to_column(start)?))
+        });
+        let file = id
+            .filter(|id| *id != world.main.id())
+            .map(|id| id.vpath().as_rootless_path().to_string_lossy().to_string());
+
+        Self {
+            error: diagnostic.severity == Severity::Error,
+            message: diagnostic.message.to_string(),
+            hints: diagnostic.hints.iter().map(|h| h.to_string()).collect(),
+            file,
+            line: position.map(|(line, _)| line + 1),
+            column: position.map(|(_, column)| column + 1),
+        }
+    }
+}
+
+pub struct Compiled {
+    pub pdf: Option<Vec<u8>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Compile a main file written by the user. It can import the template's files,
+/// e.g. `#import "kit_timesheet.typ"`, but nothing else.
+pub fn compile_source(template: &dyn Template, source: String, now: Zoned) -> Compiled {
+    let (book, fonts) = load_system_fonts();
+    let world = DummyWorld::new(template, source, book, fonts, now, vec![]);
+    let result = typst::compile::<PagedDocument>(&world);
+
+    let mut diagnostics = (result.warnings.iter())
+        .map(|warning| Diagnostic::new(&world, warning))
+        .collect::<Vec<_>>();
+    let pdf = match result
+        .output
+        .and_then(|document| typst_pdf::pdf(&document, &PdfOptions::default()))
+    {
+        Ok(pdf) => Some(pdf),
+        Err(errors) => {
+            diagnostics.extend(errors.iter().map(|error| Diagnostic::new(&world, error)));
+            None
+        }
+    };
+
+    Compiled { pdf, diagnostics }
+}
+
+//////////////////
+// Extract data //
+//////////////////
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    doc.dereference(object).ok().map(|(_, object)| object)
+}
+
+fn find_attachment(doc: &Document, name: &str) -> Option<Vec<u8>> {
+    let catalog = doc.catalog().ok()?;
+    let names = resolve(doc, catalog.get(b"Names").ok()?)?.as_dict().ok()?;
+    let files = resolve(doc, names.get(b"EmbeddedFiles").ok()?)?;
+    let files = resolve(doc, files.as_dict().ok()?.get(b"Names").ok()?)?;
+
+    // The name tree is a flat array of alternating keys and file specs.
+    for pair in files.as_array().ok()?.chunks_exact(2) {
+        let key = resolve(doc, &pair[0])?.as_str().ok()?;
+        if key != name.as_bytes() {
+            continue;
+        }
+
+        let spec = resolve(doc, &pair[1])?.as_dict().ok()?;
+        let ef = resolve(doc, spec.get(b"EF").ok()?)?.as_dict().ok()?;
+        let stream = resolve(doc, ef.get(b"F").ok()?)?.as_stream().ok()?;
+        return Some(
+            stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone()),
+        );
+    }
+
+    None
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    /// The file couldn't be parsed as a PDF.
+    InvalidPdf(String),
+    /// The PDF wasn't produced by [`render`].
+    NoTimesheetData,
+    /// The embedded data doesn't match the current [`Timesheet`].
+    InvalidTimesheetData(String),
+}
+
+impl ExtractError {
+    pub fn message(&self, m: &Messages) -> String {
+        match self {
+            Self::InvalidPdf(error) => fill(m.invalid_pdf, &[("error", error)]),
+            Self::NoTimesheetData => m.no_timesheet_data.to_string(),
+            Self::InvalidTimesheetData(error) => {
+                fill(m.invalid_timesheet_data, &[("error", error)])
+            }
+        }
+    }
+}
+
+/// In English, for logs.
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message(Lang::En.messages()))
+    }
+}
+
+/// Recover the data embedded into a PDF previously produced by [`render`].
+pub fn extract(pdf: &[u8]) -> Result<Timesheet, ExtractError> {
+    let doc = Document::load_mem(pdf).map_err(|e| ExtractError::InvalidPdf(e.to_string()))?;
+    let Some(json) = find_attachment(&doc, ATTACHMENT_NAME) else {
+        return Err(ExtractError::NoTimesheetData);
+    };
+    serde_json::from_slice(&json).map_err(|e| ExtractError::InvalidTimesheetData(e.to_string()))
+}
+
+///////////////
+// Self test //
+///////////////
+
+pub struct FontCheck {
+    pub family: String,
+    pub found: bool,
+}
+
+pub struct SelfTest {
+    /// Identifies the template's contents, to tell deployments apart.
+    pub template_version: String,
+    pub fonts: Vec<FontCheck>,
+    pub problems: Vec<String>,
+}
+
+impl SelfTest {
+    pub fn ok(&self) -> bool {
+        self.fonts.iter().all(|font| font.found) && self.problems.is_empty()
+    }
+}
+
+/// Render an empty time sheet to check that typst, the template and the system
+/// fonts work together. Missing fonts only result in a warning during
+/// compilation, so they are checked for explicitly.
+pub fn self_test(template: &dyn Template) -> SelfTest {
+    let (book, fonts) = load_system_fonts();
+    let font_checks = (template.fonts().into_iter())
+        .map(|family| FontCheck {
+            found: book.contains_family(&family.to_lowercase()),
+            family,
+        })
+        .collect();
+
+    let source = template.main_empty();
+    let world = DummyWorld::new(template, source, book, fonts, Zoned::now(), vec![]);
+    let result = typst::compile::<PagedDocument>(&world);
+
+    let mut problems = fmt_errors(&result.warnings);
+    match result.output {
+        Ok(document) => {
+            if let Err(es) = typst_pdf::pdf(&document, &PdfOptions::default()) {
+                problems.extend(fmt_errors(&es));
+            }
+        }
+        Err(es) => problems.extend(fmt_errors(&es)),
+    }
+
+    SelfTest {
+        template_version: template.version(),
+        fonts: font_checks,
+        problems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use typst::syntax::{ast, parse_code, SyntaxKind};
+
+    use super::fmt_str;
+
+    proptest! {
+        /// No input may escape the string literal it is formatted into.
+        #[test]
+        fn fmt_str_stays_a_string(s in any::<String>()) {
+            let source = fmt_str(&s);
+            let code = parse_code(&source);
+            prop_assert!(!code.erroneous(), "{:?} doesn't parse", source);
+
+            let children = code.children().collect::<Vec<_>>();
+            prop_assert_eq!(children.len(), 1, "{:?} isn't a single node", source);
+            prop_assert_eq!(children[0].kind(), SyntaxKind::Str);
+
+            let literal = children[0].cast::<ast::Str>().unwrap().get();
+            prop_assert_eq!(literal.as_str(), s.as_str());
+        }
+    }
 }
@@ -0,0 +1,545 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::{info, warn};
+use typst::foundations::Bytes;
+
+use crate::endpoints::i18n::{fill, Messages};
+
+use super::{
+    fmt_bool, fmt_date, fmt_int, fmt_str, Entry, Language, Note, Timesheet, WorkingArea,
+    SIGNATURE_HEIGHT, SIGNATURE_NAME, SUPERVISOR_SIGNATURE_NAME,
+};
+
+/// A form layout time sheets can be rendered with.
+pub trait Template: Send + Sync {
+    /// Name used to select the template, e.g. via `?template=`.
+    fn name(&self) -> &str;
+
+    /// Shown when choosing a template.
+    fn title(&self) -> &str;
+
+    /// Identifies the template's contents, to tell deployments apart.
+    fn version(&self) -> String;
+
+    /// Font families the template sets its text in.
+    fn fonts(&self) -> Vec<String>;
+
+    /// How many entries fit on the form.
+    fn rows(&self) -> usize;
+
+    /// A typst source file the main source may import.
+    fn source(&self, path: &str) -> Option<String>;
+
+    /// Any other file the template needs, like images.
+    fn file(&self, path: &str) -> Option<Bytes>;
+
+    /// Typst code filling in the form with a time sheet.
+    fn main(&self, ts: &Timesheet) -> String;
+
+    /// Typst code producing an empty form, to be filled out by hand.
+    fn main_empty(&self) -> String;
+
+    /// Check that a time sheet fits the template before spending time on
+    /// compiling it.
+    fn check(&self, ts: &Timesheet, m: &Messages) -> Result<(), String> {
+        if ts.entries.len() > self.rows() {
+            let name = format!("{:?}", self.name());
+            return Err(fill(
+                m.too_many_entries_for_template,
+                &[
+                    ("template", &name),
+                    ("entries", &ts.entries.len()),
+                    ("max", &self.rows()),
+                ],
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The templates available for rendering. The first built-in one is the
+/// default.
+pub struct Templates {
+    builtin: Vec<Arc<dyn Template>>,
+    /// Where additional templates are loaded from.
+    dir: Option<PathBuf>,
+    loaded: RwLock<Vec<Arc<dyn Template>>>,
+}
+
+impl Templates {
+    pub fn builtin() -> Self {
+        Self {
+            builtin: vec![Arc::new(Kit)],
+            dir: None,
+            loaded: RwLock::new(vec![]),
+        }
+    }
+
+    /// The built-in templates plus one template per subdirectory of `dir`.
+    pub fn with_dir(dir: PathBuf) -> anyhow::Result<Self> {
+        let templates = Self {
+            dir: Some(dir),
+            ..Self::builtin()
+        };
+        templates.reload()?;
+        Ok(templates)
+    }
+
+    pub fn default(&self) -> Arc<dyn Template> {
+        self.builtin[0].clone()
+    }
+
+    pub fn all(&self) -> Vec<Arc<dyn Template>> {
+        let loaded = self.loaded.read().unwrap();
+        self.builtin.iter().chain(loaded.iter()).cloned().collect()
+    }
+
+    /// Look up a template by name, falling back to the default.
+    pub fn get(&self, name: Option<&str>, m: &Messages) -> Result<Arc<dyn Template>, String> {
+        let Some(name) = name else {
+            return Ok(self.default());
+        };
+        (self.all().into_iter())
+            .find(|template| template.name() == name)
+            .ok_or_else(|| fill(m.unknown_template, &[("value", &format!("{name:?}"))]))
+    }
+
+    /// Load the templates from the directory again. If that fails, the
+    /// previously loaded templates are kept.
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+
+        let mut loaded = vec![];
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("failed to read templates dir {}", dir.display()))?
+        {
+            let entry = entry?;
+            // Symlinks aren't followed, see [`walk`].
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                warn!(path = %entry.path().display(), "skipping template with invalid name");
+                continue;
+            };
+            if self.builtin.iter().any(|template| template.name() == name) {
+                warn!(
+                    name = %name,
+                    "skipping template with the name of a built-in template"
+                );
+                continue;
+            }
+            match DirTemplate::load(name.clone(), &entry.path()) {
+                Ok(template) => loaded.push(Arc::new(template) as Arc<dyn Template>),
+                Err(error) => warn!(name = %name, %error, "skipping invalid template"),
+            }
+        }
+        loaded.sort_by(|a, b| a.name().cmp(b.name()));
+
+        info!(
+            templates = ?loaded.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            "loaded templates"
+        );
+        *self.loaded.write().unwrap() = loaded;
+        Ok(())
+    }
+
+    /// Reload the templates whenever a file in the directory changes. The
+    /// directory is polled, which also works for network file systems and
+    /// volumes mounted into containers.
+    pub async fn watch(self: Arc<Self>) {
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        let mut last = None;
+        loop {
+            interval.tick().await;
+            let scan_dir = dir.clone();
+            let fingerprint = tokio::task::spawn_blocking(move || fingerprint(&scan_dir))
+                .await
+                .ok()
+                .and_then(|result| result.ok());
+            if fingerprint.is_none() || fingerprint == last {
+                continue;
+            }
+            // The templates were already loaded on startup.
+            if last.is_some() {
+                let templates = self.clone();
+                match tokio::task::spawn_blocking(move || templates.reload()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => warn!(%error, "reloading templates failed"),
+                    Err(_) => warn!("reloading templates panicked"),
+                }
+            }
+            last = fingerprint;
+        }
+    }
+}
+
+///////////////////////
+// Convert to source //
+///////////////////////
+
+// Templates derived from the KIT form share its interface: a `timesheet`
+// function taking the header fields and `entry` values, plus the `areas` and
+// `notes` dictionaries.
+
+const ALIAS: &str = "ts";
+
+fn fmt_area(area: WorkingArea) -> String {
+    let name = match area {
+        WorkingArea::Großforschung => "Großforschung",
+        WorkingArea::Unibereich => "Unibereich",
+    };
+    format!("{ALIAS}.areas.{name}")
+}
+
+fn fmt_note(note: Note) -> String {
+    let name = match note {
+        Note::Urlaub => "Urlaub",
+        Note::Krankheit => "Krankheit",
+        Note::Feiertag => "Feiertag",
+        Note::Sonstiges => "Sonstiges",
+    };
+    format!("{ALIAS}.notes.{name}")
+}
+
+fn fmt_language(language: Language) -> String {
+    let name = match language {
+        Language::German => "German",
+        Language::English => "English",
+        Language::Bilingual => "Bilingual",
+    };
+    format!("{ALIAS}.languages.{name}")
+}
+
+fn fmt_entry(entry: Entry) -> String {
+    let mut args = vec![
+        fmt_str(&entry.task),
+        fmt_int(entry.day),
+        fmt_str(&entry.start),
+        fmt_str(&entry.end),
+    ];
+
+    if let Some(rest) = entry.rest {
+        args.push(format!("rest: {}", fmt_str(&rest)));
+    }
+
+    if let Some(note) = entry.note {
+        args.push(format!("note: {}", fmt_note(note)));
+    }
+
+    format!("{ALIAS}.entry({})", args.join(", "))
+}
+
+fn fmt_import(entry_point: &str) -> String {
+    format!("#import {} as {ALIAS}", fmt_str(entry_point))
+}
+
+/// Call the `timesheet` function of the template at `entry_point`.
+fn fmt_timesheet(entry_point: &str, ts: &Timesheet) -> String {
+    let mut lines = vec![];
+
+    lines.push(fmt_import(entry_point));
+    lines.push(format!("#{ALIAS}.timesheet("));
+    lines.push(format!("  name: {},", fmt_str(&ts.name)));
+    lines.push(format!("  staff_id: {},", fmt_str(&ts.staff_id)));
+    lines.push(format!("  department: {},", fmt_str(&ts.department)));
+    lines.push(format!(
+        "  working_area: {},",
+        fmt_area(ts.working_area.clone())
+    ));
+    lines.push(format!("  monthly_hours: {},", fmt_int(ts.monthly_hours)));
+    lines.push(format!("  hourly_wage: {},", fmt_str(&ts.hourly_wage)));
+    lines.push(format!("  validate: {},", fmt_bool(ts.validate)));
+    lines.push(format!("  sort: {},", fmt_bool(ts.sort)));
+    if let Some(carry) = &ts.carry_prev_month {
+        lines.push(format!("  carry_prev_month: {},", fmt_str(carry)));
+    }
+    lines.push(format!("  year: {},", fmt_int(ts.year)));
+    lines.push(format!("  month: {},", fmt_int(ts.month)));
+    lines.push(format!("  state: {},", fmt_str(&ts.holiday_state)));
+    if let Some(date) = ts.signature_date {
+        lines.push(format!("  signature_date: {},", fmt_date(date)));
+    } else if ts.date_signature {
+        lines.push("  signature_date: auto,".to_string());
+    }
+    if let Some(signature) = &ts.signature {
+        lines.push(format!(
+            "  signature: image({}, height: {SIGNATURE_HEIGHT}),",
+            fmt_str(&signature.file_name(SIGNATURE_NAME))
+        ));
+    }
+    if let Some(approval) = &ts.approval {
+        lines.push(format!("  supervisor_name: {},", fmt_str(&approval.name)));
+        lines.push(format!("  supervisor_date: {},", fmt_date(approval.date)));
+        if let Some(signature) = &approval.signature {
+            lines.push(format!(
+                "  supervisor_signature: image({}, height: {SIGNATURE_HEIGHT}),",
+                fmt_str(&signature.file_name(SUPERVISOR_SIGNATURE_NAME))
+            ));
+        }
+    }
+    // Only passed when needed, so that templates without the parameter still
+    // work for German time sheets.
+    if ts.language != Language::German {
+        lines.push(format!("  language: {},", fmt_language(ts.language)));
+    }
+    for entry in &ts.entries {
+        lines.push(format!("  {},", fmt_entry(entry.clone())));
+    }
+    lines.push(")".to_string());
+    lines.join("\n")
+}
+
+/// Call the `timesheet_empty` function of the template at `entry_point`.
+fn fmt_timesheet_empty(entry_point: &str) -> String {
+    format!("{}\n#{ALIAS}.timesheet_empty()\n", fmt_import(entry_point))
+}
+
+/////////
+// KIT //
+/////////
+
+const KIT_LOGO: &str = include_str!("../../kit_logo.svg");
+const KIT_LOGO_NAME: &str = "kit_logo.svg";
+
+const KIT_TEMPLATE: &str = include_str!("../../kit_timesheet.typ");
+const KIT_TEMPLATE_NAME: &str = "kit_timesheet.typ";
+
+/// Must match `_kit_rows` in the template.
+const KIT_ROWS: usize = 22;
+
+/// The "Arbeitszeitdokumentation" form of the Karlsruhe Institute of
+/// Technology, see `kit_timesheet.typ`.
+pub struct Kit;
+
+impl Template for Kit {
+    fn name(&self) -> &str {
+        "kit"
+    }
+
+    fn title(&self) -> &str {
+        "KIT"
+    }
+
+    fn version(&self) -> String {
+        format!("{:032x}", typst::utils::hash128(KIT_TEMPLATE))
+    }
+
+    fn fonts(&self) -> Vec<String> {
+        vec!["Liberation Sans".to_string()]
+    }
+
+    fn rows(&self) -> usize {
+        KIT_ROWS
+    }
+
+    fn source(&self, path: &str) -> Option<String> {
+        match path {
+            KIT_TEMPLATE_NAME => Some(KIT_TEMPLATE.to_string()),
+            _ => None,
+        }
+    }
+
+    fn file(&self, path: &str) -> Option<Bytes> {
+        match path {
+            KIT_LOGO_NAME => Some(Bytes::new(KIT_LOGO.as_bytes())),
+            _ => None,
+        }
+    }
+
+    fn main(&self, ts: &Timesheet) -> String {
+        fmt_timesheet(KIT_TEMPLATE_NAME, ts)
+    }
+
+    fn main_empty(&self) -> String {
+        fmt_timesheet_empty(KIT_TEMPLATE_NAME)
+    }
+}
+
+/////////////////
+// Directories //
+/////////////////
+
+/// How often the templates dir is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Guards against accidentally loading huge directories into memory.
+const MAX_DIR_TEMPLATE_BYTES: u64 = 32 * 1024 * 1024;
+
+const MANIFEST_NAME: &str = "template.toml";
+
+/// Describes a template loaded from a directory, read from its
+/// `template.toml`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// How many entries fit on the form, like `_kit_rows` in
+    /// `kit_timesheet.typ`.
+    rows: usize,
+}
+
+impl Manifest {
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let text = std::str::from_utf8(data).map_err(|_| format!("{MANIFEST_NAME} isn't UTF-8"))?;
+        let manifest = toml::from_str::<Self>(text)
+            .map_err(|e| format!("invalid {MANIFEST_NAME}: {}", e.message()))?;
+        if manifest.rows == 0 {
+            return Err(format!("invalid {MANIFEST_NAME}: rows must be at least 1"));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Call `f` with the path relative to `root` (separated by `/`) and metadata
+/// of every regular file below `root`. Symlinks are skipped, so that nothing
+/// outside of `root` can be reached.
+fn walk(
+    root: &Path,
+    f: &mut dyn FnMut(String, &Path, &fs::Metadata) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut dirs = vec![(String::new(), root.to_path_buf())];
+    while let Some((prefix, dir)) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            // Files that can't be named in typst can't be used anyway.
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push((format!("{prefix}{name}/"), path));
+            } else if file_type.is_file() {
+                f(format!("{prefix}{name}"), &path, &entry.metadata()?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Changes whenever a file below `dir` is added, removed or modified.
+fn fingerprint(dir: &Path) -> io::Result<Vec<(String, Option<SystemTime>, u64)>> {
+    let mut files = vec![];
+    walk(dir, &mut |path, _, metadata| {
+        files.push((path, metadata.modified().ok(), metadata.len()));
+        Ok(())
+    })?;
+    files.sort();
+    Ok(files)
+}
+
+/// A variant of the KIT form loaded from a directory. The directory must
+/// contain exactly one `.typ` file at its top level, which must provide the
+/// same interface as `kit_timesheet.typ`, and a [`Manifest`]. All other files
+/// below the directory can be used by the template, e.g. images or further
+/// typst files.
+struct DirTemplate {
+    name: String,
+    entry_point: String,
+    rows: usize,
+    files: HashMap<String, Bytes>,
+    version: String,
+}
+
+impl DirTemplate {
+    fn load(name: String, dir: &Path) -> Result<Self, String> {
+        let mut files = HashMap::new();
+        let mut total = 0;
+        walk(dir, &mut |path, file, metadata| {
+            total += metadata.len();
+            if total > MAX_DIR_TEMPLATE_BYTES {
+                return Err(io::Error::other(format!(
+                    "template is larger than {MAX_DIR_TEMPLATE_BYTES} bytes"
+                )));
+            }
+            files.insert(path, Bytes::new(fs::read(file)?));
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+
+        let entry_points = (files.keys())
+            .filter(|path| !path.contains('/') && path.ends_with(".typ"))
+            .cloned()
+            .collect::<Vec<_>>();
+        let [entry_point] = &entry_points[..] else {
+            return Err(format!(
+                "expected exactly one .typ file at the top level, found {}",
+                entry_points.len()
+            ));
+        };
+
+        let manifest = files
+            .get(MANIFEST_NAME)
+            .ok_or_else(|| format!("{MANIFEST_NAME} is missing"))?;
+        let manifest = Manifest::parse(manifest)?;
+
+        let mut paths = files.keys().collect::<Vec<_>>();
+        paths.sort();
+        let contents = (paths.into_iter())
+            .map(|path| (path, &files[path][..]))
+            .collect::<Vec<_>>();
+        let version = format!("{:032x}", typst::utils::hash128(&contents));
+
+        Ok(Self {
+            name,
+            entry_point: entry_point.clone(),
+            rows: manifest.rows,
+            files,
+            version,
+        })
+    }
+}
+
+impl Template for DirTemplate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn title(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> String {
+        self.version.clone()
+    }
+
+    fn fonts(&self) -> Vec<String> {
+        // Unknown without compiling the template.
+        vec![]
+    }
+
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn source(&self, path: &str) -> Option<String> {
+        let data = self.files.get(path)?;
+        String::from_utf8(data.to_vec()).ok()
+    }
+
+    fn file(&self, path: &str) -> Option<Bytes> {
+        self.files.get(path).cloned()
+    }
+
+    fn main(&self, ts: &Timesheet) -> String {
+        fmt_timesheet(&self.entry_point, ts)
+    }
+
+    fn main_empty(&self) -> String {
+        fmt_timesheet_empty(&self.entry_point)
+    }
+}
@@ -0,0 +1,244 @@
+use std::path::Path;
+
+use jiff::Timestamp;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    render::{Signature, Timesheet},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS reviews (
+    id               TEXT NOT NULL PRIMARY KEY,
+    token            TEXT NOT NULL UNIQUE,
+    template         TEXT NOT NULL,
+    timesheet        TEXT NOT NULL,
+    signature        TEXT,
+    employee_email   TEXT,
+    supervisor_email TEXT,
+    status           TEXT NOT NULL,
+    approved_by      TEXT,
+    comment          TEXT,
+    pdf              BLOB NOT NULL,
+    submitted_at     TEXT NOT NULL,
+    decided_at       TEXT
+) STRICT;
+";
+
+const COLUMNS: &str = "id, token, template, timesheet, signature, employee_email, \
+                       supervisor_email, status, approved_by, comment, pdf";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl Status {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(Self::Pending),
+            "approved" => Some(Self::Approved),
+            "rejected" => Some(Self::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// A time sheet submitted for review by the employee's supervisor.
+pub struct Review {
+    /// Public part of the links, known to the employee.
+    pub id: String,
+    /// Secret part of the supervisor's link. Whoever knows it can decide.
+    pub token: String,
+    /// Name of the template the time sheet was submitted with.
+    pub template: String,
+    pub timesheet: Timesheet,
+    pub employee_email: Option<String>,
+    pub supervisor_email: Option<String>,
+    pub status: Status,
+    /// Name of the supervisor who approved the time sheet.
+    pub approved_by: Option<String>,
+    /// Why the time sheet was rejected.
+    pub comment: Option<String>,
+    /// The submitted document, replaced by the final one once approved.
+    pub pdf: Vec<u8>,
+}
+
+fn conversion_error(
+    column: usize,
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, error.into())
+}
+
+impl Review {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let timesheet = row.get::<_, String>(3)?;
+        let mut timesheet =
+            serde_json::from_str::<Timesheet>(&timesheet).map_err(|e| conversion_error(3, e))?;
+        // The signature image isn't part of the serialized time sheet.
+        if let Some(signature) = row.get::<_, Option<String>>(4)? {
+            timesheet.signature =
+                Some(Signature::parse_data_url(&signature).map_err(|e| conversion_error(4, e))?);
+        }
+
+        let status = row.get::<_, String>(7)?;
+        let status = Status::parse(&status)
+            .ok_or_else(|| conversion_error(7, format!("invalid status: {status:?}")))?;
+
+        Ok(Self {
+            id: row.get(0)?,
+            token: row.get(1)?,
+            template: row.get(2)?,
+            timesheet,
+            employee_email: row.get(5)?,
+            supervisor_email: row.get(6)?,
+            status,
+            approved_by: row.get(8)?,
+            comment: row.get(9)?,
+            pdf: row.get(10)?,
+        })
+    }
+}
+
+/// Submitted time sheets, stored in a SQLite database.
+pub struct Reviews {
+    db: Database,
+}
+
+impl Reviews {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::open(path, SCHEMA)?;
+        Ok(Self { db })
+    }
+
+    /// Store a newly submitted time sheet along with its rendered document,
+    /// returning its id and review token.
+    pub async fn submit(
+        &self,
+        template: &str,
+        timesheet: &Timesheet,
+        pdf: Vec<u8>,
+        employee_email: Option<String>,
+        supervisor_email: Option<String>,
+    ) -> rusqlite::Result<(String, String)> {
+        let id = Uuid::new_v4().simple().to_string();
+        let token = Uuid::new_v4().simple().to_string();
+        let template = template.to_string();
+        // Serializing plain data to JSON can't fail.
+        let json = serde_json::to_string(timesheet).unwrap();
+        let signature = timesheet.signature.as_ref().map(Signature::to_data_url);
+
+        let ids = (id.clone(), token.clone());
+        self.db
+            .run(move |db| {
+                db.execute(
+                    "INSERT INTO reviews (id, token, template, timesheet, signature, \
+                     employee_email, supervisor_email, status, pdf, submitted_at) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        id,
+                        token,
+                        template,
+                        json,
+                        signature,
+                        employee_email,
+                        supervisor_email,
+                        Status::Pending.name(),
+                        pdf,
+                        Timestamp::now().to_string(),
+                    ],
+                )
+            })
+            .await?;
+
+        Ok(ids)
+    }
+
+    async fn get_by(&self, column: &'static str, value: &str) -> rusqlite::Result<Option<Review>> {
+        let value = value.to_string();
+        self.db
+            .run(move |db| {
+                db.query_row(
+                    &format!("SELECT {COLUMNS} FROM reviews WHERE {column} = ?1"),
+                    [value],
+                    Review::from_row,
+                )
+                .optional()
+            })
+            .await
+    }
+
+    pub async fn get(&self, id: &str) -> rusqlite::Result<Option<Review>> {
+        self.get_by("id", id).await
+    }
+
+    pub async fn get_by_token(&self, token: &str) -> rusqlite::Result<Option<Review>> {
+        self.get_by("token", token).await
+    }
+
+    /// Replace the document with the approved one. Returns `false` if the
+    /// review was already decided.
+    pub async fn approve(
+        &self,
+        id: &str,
+        approved_by: &str,
+        pdf: Vec<u8>,
+    ) -> rusqlite::Result<bool> {
+        let id = id.to_string();
+        let approved_by = approved_by.to_string();
+        let changed = self
+            .db
+            .run(move |db| {
+                db.execute(
+                    "UPDATE reviews SET status = ?2, approved_by = ?3, pdf = ?4, \
+                     decided_at = ?5 WHERE id = ?1 AND status = ?6",
+                    params![
+                        id,
+                        Status::Approved.name(),
+                        approved_by,
+                        pdf,
+                        Timestamp::now().to_string(),
+                        Status::Pending.name(),
+                    ],
+                )
+            })
+            .await?;
+        Ok(changed > 0)
+    }
+
+    /// Returns `false` if the review was already decided.
+    pub async fn reject(&self, id: &str, comment: &str) -> rusqlite::Result<bool> {
+        let id = id.to_string();
+        let comment = comment.to_string();
+        let changed = self
+            .db
+            .run(move |db| {
+                db.execute(
+                    "UPDATE reviews SET status = ?2, comment = ?3, decided_at = ?4 \
+                     WHERE id = ?1 AND status = ?5",
+                    params![
+                        id,
+                        Status::Rejected.name(),
+                        comment,
+                        Timestamp::now().to_string(),
+                        Status::Pending.name(),
+                    ],
+                )
+            })
+            .await?;
+        Ok(changed > 0)
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    env, io,
+    io::{Read, Write},
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::Config;
+
+/// How often to check whether the child process has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub enum SandboxError {
+    /// The child process couldn't be started or talked to.
+    Io(io::Error),
+    /// Compiling took longer than the configured timeout.
+    Timeout,
+    /// The child process died, most likely because it ran out of memory and
+    /// either aborted or was killed by the kernel.
+    Aborted,
+}
+
+impl SandboxError {
+    /// Short name for logs and metrics.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "sandbox",
+            Self::Timeout => "timeout",
+            Self::Aborted => "memory",
+        }
+    }
+}
+
+/// Compiles untrusted typst sources in a child process with limited time and
+/// memory, since typst itself can't be interrupted or stopped from allocating.
+///
+/// The child is this binary, started with the hidden `compile` subcommand.
+pub struct Sandbox {
+    timeout: Duration,
+    max_memory_bytes: u64,
+    templates_dir: Option<PathBuf>,
+    time_zone: String,
+}
+
+impl Sandbox {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.sandbox.timeout),
+            max_memory_bytes: config.sandbox.max_memory_mib * 1024 * 1024,
+            templates_dir: config.templates_dir.clone(),
+            time_zone: config.time_zone.clone(),
+        }
+    }
+
+    fn command(&self, template: &str) -> io::Result<Command> {
+        let mut command = Command::new(env::current_exe()?);
+        command.arg("compile").arg("--template").arg(template);
+        command.arg("--time-zone").arg(&self.time_zone);
+        if let Some(dir) = &self.templates_dir {
+            command.arg("--templates-dir").arg(dir);
+        }
+
+        // The server's own settings would conflict with the subcommand.
+        command.env_clear().envs(
+            env::vars_os().filter(|(key, _)| !key.to_string_lossy().starts_with("ABZDOKGEN_")),
+        );
+        // The memory limit covers address space, which every thread and malloc
+        // arena reserves plenty of.
+        command
+            .env("RAYON_NUM_THREADS", "1")
+            .env("MALLOC_ARENA_MAX", "1");
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        // The CPU limit is only a backstop, the timeout below is usually hit
+        // first.
+        let memory = self.max_memory_bytes as libc::rlim_t;
+        let cpu = self.timeout.as_secs() as libc::rlim_t + 1;
+        // SAFETY: setrlimit is async-signal-safe and nothing is allocated.
+        unsafe {
+            command.pre_exec(move || {
+                // Reaching the hard CPU limit sends SIGKILL instead of SIGXCPU,
+                // which would look like the OOM killer.
+                let limits = [
+                    (libc::RLIMIT_AS, memory, memory),
+                    (libc::RLIMIT_CPU, cpu, cpu + 1),
+                ];
+                for (resource, soft, hard) in limits {
+                    let limit = libc::rlimit {
+                        rlim_cur: soft,
+                        rlim_max: hard,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+
+        Ok(command)
+    }
+
+    /// Compile a main file written by the user, returning the child's JSON
+    /// output. Blocks until the child has exited.
+    pub fn compile(&self, template: &str, source: &[u8]) -> Result<Vec<u8>, SandboxError> {
+        let start = Instant::now();
+        let mut child = self
+            .command(template)
+            .and_then(|mut command| command.spawn())
+            .map_err(SandboxError::Io)?;
+
+        // The child reads all of its input before writing anything, so this
+        // can't deadlock. Its output may be larger than the pipe buffer though,
+        // so it is read on a separate thread while waiting.
+        let mut stdin = child.stdin.take().unwrap();
+        let written = stdin.write_all(source);
+        drop(stdin);
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut output = vec![];
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if start.elapsed() < self.timeout => thread::sleep(POLL_INTERVAL),
+                Ok(None) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(SandboxError::Timeout);
+                }
+                Err(error) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(SandboxError::Io(error));
+                }
+            }
+        };
+        // Reading into a vector doesn't panic.
+        let output = reader.join().unwrap();
+
+        // A SIGKILL can't come from the timeout since that returns above, so it
+        // is the kernel's OOM killer.
+        match status.signal() {
+            Some(libc::SIGXCPU) => return Err(SandboxError::Timeout),
+            Some(_) => return Err(SandboxError::Aborted),
+            None if !status.success() => return Err(SandboxError::Aborted),
+            None => {}
+        }
+        written.map_err(SandboxError::Io)?;
+        output.map_err(SandboxError::Io)
+    }
+}
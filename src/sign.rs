@@ -0,0 +1,351 @@
+use std::{collections::BTreeSet, fmt};
+
+use jiff::{tz::TimeZone, Zoned};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use p12_keystore::KeyStore;
+use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+
+use crate::endpoints::i18n::{fill, Lang, Messages};
+
+use self::update::Update;
+
+mod cms;
+mod der;
+mod update;
+
+/// Space reserved for the CMS signature, which mostly consists of the
+/// certificate chain.
+const SIGNATURE_BYTES: usize = 16 * 1024;
+
+/// Space reserved for the byte range until the final layout is known, enough
+/// for four offsets of up to ten digits each.
+const BYTE_RANGE_WIDTH: usize = 4 * 10 + 3;
+
+/// A private key and its certificate chain, loaded from a PKCS#12 file.
+pub struct Identity {
+    key: RsaPrivateKey,
+    /// Starts with the certificate belonging to the key.
+    chain: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub enum CertificateError {
+    /// Either the file is corrupt or the password is wrong, PKCS#12 can't
+    /// tell those apart.
+    Invalid,
+    NoPrivateKey,
+    UnsupportedKey,
+    NoCertificate,
+}
+
+impl CertificateError {
+    pub fn message(&self, m: &Messages) -> &'static str {
+        match self {
+            Self::Invalid => m.invalid_certificate,
+            Self::NoPrivateKey => m.no_private_key,
+            Self::UnsupportedKey => m.unsupported_key,
+            Self::NoCertificate => m.no_certificate,
+        }
+    }
+}
+
+/// In English, for the CLI and logs.
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message(Lang::En.messages()))
+    }
+}
+
+impl Identity {
+    pub fn from_pkcs12(data: &[u8], password: &str) -> Result<Self, CertificateError> {
+        let keystore =
+            KeyStore::from_pkcs12(data, password).map_err(|_| CertificateError::Invalid)?;
+        let (_, chain) = keystore
+            .private_key_chain()
+            .ok_or(CertificateError::NoPrivateKey)?;
+        let key = RsaPrivateKey::from_pkcs8_der(chain.key())
+            .map_err(|_| CertificateError::UnsupportedKey)?;
+        let chain = (chain.chain().iter())
+            .map(|certificate| certificate.as_der().to_vec())
+            .collect::<Vec<_>>();
+        if chain.is_empty() {
+            return Err(CertificateError::NoCertificate);
+        }
+        Ok(Self { key, chain })
+    }
+
+    pub fn name(&self) -> Option<String> {
+        cms::common_name(&self.chain[0])
+    }
+}
+
+fn fmt_pdf_date(time: &Zoned) -> String {
+    time.with_time_zone(TimeZone::UTC)
+        .strftime("D:%Y%m%d%H%M%S+00'00'")
+        .to_string()
+}
+
+/// Append `item` to the array stored under `key` in `dict`, either directly or
+/// in a separate object. `owner` is the object `dict` belongs to.
+fn push_to_array(
+    doc: &mut Document,
+    dict: &mut Dictionary,
+    owner: ObjectId,
+    key: &[u8],
+    item: Object,
+    changed: &mut BTreeSet<ObjectId>,
+) -> Result<(), String> {
+    match dict.get(key).ok().cloned() {
+        Some(Object::Reference(id)) => {
+            (doc.get_object_mut(id).and_then(Object::as_array_mut))
+                .map_err(|e| e.to_string())?
+                .push(item);
+            changed.insert(id);
+        }
+        Some(Object::Array(mut items)) => {
+            items.push(item);
+            dict.set(key, items);
+            changed.insert(owner);
+        }
+        _ => {
+            dict.set(key, vec![item]);
+            changed.insert(owner);
+        }
+    }
+    Ok(())
+}
+
+fn array_len(doc: &Document, object: Option<&Object>) -> usize {
+    match object {
+        Some(Object::Reference(id)) => {
+            (doc.get_object(*id).and_then(Object::as_array)).map_or(0, Vec::len)
+        }
+        Some(Object::Array(items)) => items.len(),
+        _ => 0,
+    }
+}
+
+/// Add an invisible signature field to the first page whose value is the
+/// signature dictionary `signature`. Returns the ids of all objects that
+/// were added or changed.
+fn add_signature_field(
+    doc: &mut Document,
+    signature: ObjectId,
+) -> Result<BTreeSet<ObjectId>, String> {
+    let err = |e: lopdf::Error| e.to_string();
+    let mut changed = BTreeSet::new();
+
+    let root = (doc.trailer.get(b"Root").and_then(Object::as_reference)).map_err(err)?;
+    let mut catalog = doc.get_dictionary(root).map_err(err)?.clone();
+    // Documents signed before already have a form with a signature field.
+    let (form_id, mut form) = match catalog.get(b"AcroForm").ok().cloned() {
+        Some(Object::Reference(id)) => (id, doc.get_dictionary(id).map_err(err)?.clone()),
+        Some(Object::Dictionary(form)) => (root, form),
+        _ => (root, Dictionary::new()),
+    };
+    let fields = array_len(doc, form.get(b"Fields").ok());
+
+    let page: ObjectId = *doc.get_pages().get(&1).ok_or("document has no pages")?;
+    let field = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "FT" => "Sig",
+        // Fields with the same name would share their value.
+        "T" => Object::string_literal(format!("Signature{}", fields + 1)),
+        "V" => signature,
+        "F" => Object::Integer(132), // Print and Locked
+        "Rect" => vec![Object::Integer(0); 4],
+        "P" => page,
+    });
+    changed.insert(field);
+
+    let mut page_dict = doc.get_dictionary(page).map_err(err)?.clone();
+    push_to_array(
+        doc,
+        &mut page_dict,
+        page,
+        b"Annots",
+        field.into(),
+        &mut changed,
+    )?;
+    doc.objects.insert(page, Object::Dictionary(page_dict));
+
+    push_to_array(
+        doc,
+        &mut form,
+        form_id,
+        b"Fields",
+        field.into(),
+        &mut changed,
+    )?;
+    form.set("SigFlags", Object::Integer(3)); // SignaturesExist and AppendOnly
+    changed.insert(form_id);
+    match form_id == root {
+        true => {
+            catalog.set("AcroForm", form);
+            doc.objects.insert(root, Object::Dictionary(catalog));
+        }
+        false => {
+            doc.objects.insert(form_id, Object::Dictionary(form));
+        }
+    }
+
+    Ok(changed)
+}
+
+fn digest_byte_range(pdf: &[u8], range: [usize; 4]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(&pdf[range[0]..range[0] + range[1]]);
+    hasher.update(&pdf[range[2]..range[2] + range[3]]);
+    hasher.finalize().to_vec()
+}
+
+/// Sign a PDF with a PAdES baseline signature covering the whole document.
+///
+/// The signature is appended as an incremental update, so signatures the PDF
+/// already has stay valid.
+pub fn sign_pdf(pdf: &[u8], identity: &Identity) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf).map_err(|e| e.to_string())?;
+    if doc.trailer.has(b"Encrypt") {
+        return Err("encrypted documents are not supported".to_string());
+    }
+    let signature = doc.new_object_id();
+    let changed = add_signature_field(&mut doc, signature)?;
+
+    let mut update = Update::new(pdf)?;
+    for id in changed {
+        let object = doc.get_object(id).map_err(|e| e.to_string())?;
+        update.write_object(id, object)?;
+    }
+
+    // The signature dictionary is written by hand to know where the byte
+    // range and contents end up.
+    let mut dict = b"<< /Type /Sig /Filter /Adobe.PPKLite /SubFilter /ETSI.CAdES.detached".to_vec();
+    dict.extend_from_slice(b" /M ");
+    update::write(
+        &mut dict,
+        &Object::string_literal(fmt_pdf_date(&Zoned::now())),
+    )?;
+    if let Some(name) = identity.name() {
+        dict.extend_from_slice(b" /Name ");
+        update::write(&mut dict, &Object::string_literal(name))?;
+    }
+    dict.extend_from_slice(b" /ByteRange [");
+    let byte_range_start = dict.len();
+    dict.extend_from_slice(&[b' '; BYTE_RANGE_WIDTH]);
+    dict.extend_from_slice(b"] /Contents ");
+    let contents_start = dict.len();
+    dict.push(b'<');
+    dict.extend_from_slice(&vec![b'0'; 2 * SIGNATURE_BYTES]);
+    dict.push(b'>');
+    let contents_end = dict.len();
+    dict.extend_from_slice(b" >>");
+
+    let offset = update.write_raw(signature, &dict);
+    let mut pdf = update.finish(&doc.trailer)?;
+
+    // Now that the layout is fixed, the signature can cover everything except
+    // its own hex string.
+    let (contents_start, contents_end) = (offset + contents_start, offset + contents_end);
+    let range = [0, contents_start, contents_end, pdf.len() - contents_end];
+    let byte_range = format!("{} {} {} {}", range[0], range[1], range[2], range[3]);
+    if byte_range.len() > BYTE_RANGE_WIDTH {
+        return Err("byte range placeholder is too small".to_string());
+    }
+    let byte_range_start = offset + byte_range_start;
+    pdf[byte_range_start..byte_range_start + BYTE_RANGE_WIDTH]
+        .copy_from_slice(format!("{byte_range:width$}", width = BYTE_RANGE_WIDTH).as_bytes());
+
+    let digest = digest_byte_range(&pdf, range);
+    let cms = cms::sign(&identity.key, &identity.chain, &digest)?;
+    if cms.len() > SIGNATURE_BYTES {
+        return Err("certificate chain is too large".to_string());
+    }
+    let hex = cms.iter().map(|b| format!("{b:02X}")).collect::<String>();
+    pdf[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+
+    Ok(pdf)
+}
+
+pub struct Verification {
+    /// Common name of the signer's certificate.
+    pub signer: Option<String>,
+    /// The signing time as claimed in the document.
+    pub signed_at: Option<String>,
+    /// Whether the signed bytes are unchanged and the signature is valid.
+    pub intact: bool,
+    /// Whether the signature covers the whole file, i.e. nothing was appended
+    /// after signing.
+    pub covers_whole_document: bool,
+}
+
+fn byte_range(signature: &lopdf::Dictionary, len: usize) -> Option<[usize; 4]> {
+    let values = signature.get(b"ByteRange").ok()?.as_array().ok()?;
+    let values = (values.iter())
+        .map(|value| usize::try_from(value.as_i64().ok()?).ok())
+        .collect::<Option<Vec<_>>>()?;
+    let range: [usize; 4] = values.try_into().ok()?;
+    let in_bounds =
+        range[0].checked_add(range[1])? <= range[2] && range[2].checked_add(range[3])? <= len;
+    in_bounds.then_some(range)
+}
+
+fn verify_signature(pdf: &[u8], signature: &lopdf::Dictionary) -> Result<Verification, String> {
+    let range = byte_range(signature, pdf.len()).ok_or("invalid byte range")?;
+    let contents = (signature.get(b"Contents").and_then(Object::as_str))
+        .map_err(|_| "signature contents missing".to_string())?;
+
+    let digest = digest_byte_range(pdf, range);
+    let verified = cms::verify(contents, &digest)?;
+
+    let signed_at = (signature.get(b"M").and_then(Object::as_str))
+        .ok()
+        .map(|time| String::from_utf8_lossy(time).to_string());
+
+    Ok(Verification {
+        signer: verified.signer,
+        signed_at,
+        intact: verified.digest_matches && verified.signature_valid,
+        covers_whole_document: range[0] == 0 && range[2] + range[3] == pdf.len(),
+    })
+}
+
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The file couldn't be parsed as a PDF.
+    InvalidPdf(String),
+    /// A signature is malformed, as opposed to not matching the document.
+    InvalidSignature(String),
+}
+
+impl VerifyError {
+    pub fn message(&self, m: &Messages) -> String {
+        match self {
+            Self::InvalidPdf(error) => fill(m.invalid_pdf, &[("error", error)]),
+            Self::InvalidSignature(error) => fill(m.invalid_signature, &[("error", error)]),
+        }
+    }
+}
+
+/// In English, for logs.
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message(Lang::En.messages()))
+    }
+}
+
+/// Check all signatures in a PDF. This only checks that the document wasn't
+/// modified after signing, not whether the signer's certificate is trusted.
+pub fn verify_pdf(pdf: &[u8]) -> Result<Vec<Verification>, VerifyError> {
+    let doc = Document::load_mem(pdf).map_err(|e| VerifyError::InvalidPdf(e.to_string()))?;
+    (doc.objects.values())
+        .filter_map(|object| object.as_dict().ok())
+        .filter(|dict| {
+            dict.get(b"Type")
+                .and_then(Object::as_name)
+                .is_ok_and(|name| name == b"Sig")
+        })
+        .map(|signature| verify_signature(pdf, signature).map_err(VerifyError::InvalidSignature))
+        .collect()
+}
@@ -0,0 +1,226 @@
+use rsa::{
+    pkcs1v15::{Signature, SigningKey, VerifyingKey},
+    pkcs8::DecodePublicKey,
+    signature::{SignatureEncoding, Signer, Verifier},
+    RsaPrivateKey, RsaPublicKey,
+};
+use sha2::{Digest, Sha256};
+
+use super::der::{self, Tlv};
+
+// Object identifiers, already DER-encoded.
+const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_CONTENT_TYPE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+const OID_MESSAGE_DIGEST: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04];
+const OID_SIGNING_CERTIFICATE_V2: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x02, 0x2f,
+];
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+/// The parts of an X.509 certificate needed for signing and verifying.
+struct CertificateFields<'a> {
+    serial: Tlv<'a>,
+    issuer: Tlv<'a>,
+    subject: Tlv<'a>,
+    public_key: Tlv<'a>,
+}
+
+fn certificate_fields(certificate: &[u8]) -> Result<CertificateFields<'_>, String> {
+    let certificate = der::expect(certificate, der::SEQUENCE)?;
+    let tbs = *der::children(certificate)?
+        .first()
+        .ok_or("invalid certificate")?;
+    let fields = der::children(tbs)?;
+
+    // The version is optional and the only field tagged [0].
+    let offset = match fields.first() {
+        Some(field) if field.tag == der::CONTEXT_0 => 1,
+        _ => 0,
+    };
+    let field = |i: usize| {
+        fields
+            .get(offset + i)
+            .copied()
+            .ok_or_else(|| "invalid certificate".to_string())
+    };
+
+    Ok(CertificateFields {
+        serial: field(0)?,
+        issuer: field(2)?,
+        subject: field(4)?,
+        public_key: field(5)?,
+    })
+}
+
+/// The common name (CN) of a certificate's subject.
+pub fn common_name(certificate: &[u8]) -> Option<String> {
+    let subject = certificate_fields(certificate).ok()?.subject;
+    for rdn in der::children(subject).ok()? {
+        for attribute in der::children(rdn).ok()? {
+            let [oid, value] = der::children(attribute).ok()?[..] else {
+                continue;
+            };
+            if oid.content == OID_COMMON_NAME
+                && [der::UTF8_STRING, der::PRINTABLE_STRING, der::IA5_STRING].contains(&value.tag)
+            {
+                return Some(String::from_utf8_lossy(value.content).to_string());
+            }
+        }
+    }
+    None
+}
+
+fn algorithm_sha256() -> Vec<u8> {
+    der::sequence(&[&der::oid(OID_SHA256)])
+}
+
+fn algorithm_rsa() -> Vec<u8> {
+    der::sequence(&[&der::oid(OID_RSA_ENCRYPTION), &der::tlv(der::NULL, &[])])
+}
+
+fn attribute(oid: &[u8], value: &[u8]) -> Vec<u8> {
+    der::sequence(&[&der::oid(oid), &der::set_of(vec![value.to_vec()])])
+}
+
+/// Binds the signature to the signer's certificate, required by PAdES.
+fn signing_certificate(certificate: &[u8]) -> Vec<u8> {
+    // The hash algorithm defaults to SHA-256 and is left out.
+    let hash = der::tlv(der::OCTET_STRING, &Sha256::digest(certificate));
+    let cert_id = der::sequence(&[&hash]);
+    der::sequence(&[&der::sequence(&[&cert_id])])
+}
+
+/// Create a detached CMS signature over a SHA-256 digest, suitable for the
+/// `ETSI.CAdES.detached` PDF signature format. The first certificate of the
+/// chain must belong to the key.
+pub fn sign(key: &RsaPrivateKey, chain: &[Vec<u8>], digest: &[u8]) -> Result<Vec<u8>, String> {
+    let certificate = chain.first().ok_or("no certificate")?;
+    let fields = certificate_fields(certificate)?;
+
+    let signed_attributes = der::set_of(vec![
+        attribute(OID_CONTENT_TYPE, &der::oid(OID_DATA)),
+        attribute(OID_MESSAGE_DIGEST, &der::tlv(der::OCTET_STRING, digest)),
+        attribute(
+            OID_SIGNING_CERTIFICATE_V2,
+            &signing_certificate(certificate),
+        ),
+    ]);
+    let signature = SigningKey::<Sha256>::new(key.clone())
+        .try_sign(&signed_attributes)
+        .map_err(|e| e.to_string())?
+        .to_vec();
+
+    // The attributes are signed as a SET, but stored tagged [0] IMPLICIT.
+    let mut stored_attributes = signed_attributes;
+    stored_attributes[0] = der::CONTEXT_0;
+
+    let signer_info = der::sequence(&[
+        &der::small_integer(1),
+        &der::sequence(&[fields.issuer.raw, fields.serial.raw]),
+        &algorithm_sha256(),
+        &stored_attributes,
+        &algorithm_rsa(),
+        &der::tlv(der::OCTET_STRING, &signature),
+    ]);
+
+    let certificates = chain.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+    let signed_data = der::sequence(&[
+        &der::small_integer(1),
+        &der::set_of(vec![algorithm_sha256()]),
+        &der::sequence(&[&der::oid(OID_DATA)]),
+        &der::constructed(der::CONTEXT_0, &certificates),
+        &der::set_of(vec![signer_info]),
+    ]);
+
+    Ok(der::sequence(&[
+        &der::oid(OID_SIGNED_DATA),
+        &der::constructed(der::CONTEXT_0, &[&signed_data]),
+    ]))
+}
+
+pub struct Verified {
+    /// Whether the signature was made over the given digest.
+    pub digest_matches: bool,
+    /// Whether the signature was made by the key of the included certificate.
+    pub signature_valid: bool,
+    pub signer: Option<String>,
+}
+
+fn message_digest(attributes: Tlv<'_>) -> Result<Option<&[u8]>, String> {
+    for attribute in der::children(attributes)? {
+        let [oid, values] = der::children(attribute)?[..] else {
+            return Err("invalid signed attribute".to_string());
+        };
+        if oid.content == OID_MESSAGE_DIGEST {
+            let value = der::expect(values.content, der::OCTET_STRING)?;
+            return Ok(Some(value.content));
+        }
+    }
+    Ok(None)
+}
+
+/// Check a detached CMS signature as created by [`sign`] against a SHA-256
+/// digest. This doesn't check whether the certificate itself is trusted.
+pub fn verify(cms: &[u8], digest: &[u8]) -> Result<Verified, String> {
+    let invalid = || "invalid CMS signature".to_string();
+
+    // The signature may be followed by padding.
+    let (content_info, _) = der::read(cms)?;
+    let [content_type, content] = der::children(content_info)?[..] else {
+        return Err(invalid());
+    };
+    if content_type.content != OID_SIGNED_DATA {
+        return Err(invalid());
+    }
+
+    let signed_data = der::expect(content.content, der::SEQUENCE)?;
+    let parts = der::children(signed_data)?;
+    let certificates = match parts.iter().find(|part| part.tag == der::CONTEXT_0) {
+        Some(certificates) => der::children(*certificates)?,
+        None => vec![],
+    };
+    let signer_infos = parts.last().ok_or_else(invalid)?;
+    let signer_info = *der::children(*signer_infos)?.first().ok_or_else(invalid)?;
+
+    let [_, sid, _, attributes, _, signature, ..] = der::children(signer_info)?[..] else {
+        return Err(invalid());
+    };
+    if attributes.tag != der::CONTEXT_0 {
+        return Err("signature has no signed attributes".to_string());
+    }
+    let [issuer, serial] = der::children(sid)?[..] else {
+        return Err(invalid());
+    };
+
+    let certificate = certificates
+        .iter()
+        .find(|certificate| {
+            certificate_fields(certificate.raw).is_ok_and(|fields| {
+                fields.issuer.raw == issuer.raw && fields.serial.raw == serial.raw
+            })
+        })
+        .ok_or("signer certificate is missing")?;
+    let fields = certificate_fields(certificate.raw)?;
+
+    let digest_matches = message_digest(attributes)? == Some(digest);
+
+    let signed = der::tlv(der::SET, attributes.content);
+    let signature_valid = match (
+        RsaPublicKey::from_public_key_der(fields.public_key.raw),
+        Signature::try_from(signature.content),
+    ) {
+        (Ok(key), Ok(signature)) => VerifyingKey::<Sha256>::new(key)
+            .verify(&signed, &signature)
+            .is_ok(),
+        _ => false,
+    };
+
+    Ok(Verified {
+        digest_matches,
+        signature_valid,
+        signer: common_name(certificate.raw),
+    })
+}
@@ -0,0 +1,112 @@
+// Just enough DER to build and take apart CMS signatures and X.509
+// certificates. Only low tag numbers and definite lengths are supported.
+
+pub const INTEGER: u8 = 0x02;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const UTF8_STRING: u8 = 0x0c;
+pub const PRINTABLE_STRING: u8 = 0x13;
+pub const IA5_STRING: u8 = 0x16;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+/// `[0]`, constructed.
+pub const CONTEXT_0: u8 = 0xa0;
+
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut result = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        result.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        result.push(0x80 | (bytes.len() - skip) as u8);
+        result.extend_from_slice(&bytes[skip..]);
+    }
+    result.extend_from_slice(content);
+    result
+}
+
+pub fn constructed(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+pub fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    constructed(SEQUENCE, parts)
+}
+
+/// A DER `SET OF`, whose elements must be sorted by their encoding.
+pub fn set_of(mut parts: Vec<Vec<u8>>) -> Vec<u8> {
+    parts.sort();
+    tlv(SET, &parts.concat())
+}
+
+pub fn small_integer(n: u8) -> Vec<u8> {
+    tlv(INTEGER, &[n])
+}
+
+pub fn oid(encoded: &[u8]) -> Vec<u8> {
+    tlv(OID, encoded)
+}
+
+#[derive(Clone, Copy)]
+pub struct Tlv<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// The whole element, including tag and length.
+    pub raw: &'a [u8],
+}
+
+/// Read a single element, returning it along with the remaining input.
+pub fn read(input: &[u8]) -> Result<(Tlv<'_>, &[u8]), String> {
+    let truncated = || "truncated DER element".to_string();
+
+    let (&tag, rest) = input.split_first().ok_or_else(truncated)?;
+    let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return Err("unsupported DER length".to_string());
+        }
+        let bytes = rest.get(..n).ok_or_else(truncated)?;
+        rest = &rest[n..];
+        bytes.iter().fold(0, |len, b| len << 8 | *b as usize)
+    };
+
+    let content = rest.get(..len).ok_or_else(truncated)?;
+    let header = input.len() - rest.len();
+    let element = Tlv {
+        tag,
+        content,
+        raw: &input[..header + len],
+    };
+    Ok((element, &rest[len..]))
+}
+
+/// Read a single element that must have the given tag.
+pub fn expect(input: &[u8], tag: u8) -> Result<Tlv<'_>, String> {
+    let (element, _) = read(input)?;
+    if element.tag != tag {
+        return Err(format!(
+            "expected DER tag {tag:#04x}, found {:#04x}",
+            element.tag
+        ));
+    }
+    Ok(element)
+}
+
+/// All elements inside a constructed element.
+pub fn children(element: Tlv<'_>) -> Result<Vec<Tlv<'_>>, String> {
+    let mut result = vec![];
+    let mut rest = element.content;
+    while !rest.is_empty() {
+        let (child, remaining) = read(rest)?;
+        result.push(child);
+        rest = remaining;
+    }
+    Ok(result)
+}
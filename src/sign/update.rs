@@ -0,0 +1,176 @@
+//! Incremental updates, which append changed objects to a PDF instead of
+//! rewriting it. Everything before the update stays byte for byte the same, so
+//! earlier signatures remain valid.
+
+use std::collections::BTreeMap;
+
+use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+
+/// Whether a byte can appear in a name without escaping.
+fn is_regular(byte: u8) -> bool {
+    (b'!'..=b'~').contains(&byte) && !b"()<>[]{}/%#".contains(&byte)
+}
+
+/// Serialize an object the way it appears in a PDF file. Streams aren't
+/// supported since updating them is never necessary for signing.
+pub fn write(out: &mut Vec<u8>, object: &Object) -> Result<(), String> {
+    match object {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Boolean(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        Object::Integer(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        Object::Real(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        Object::Name(name) => {
+            out.push(b'/');
+            for &byte in name {
+                match is_regular(byte) {
+                    true => out.push(byte),
+                    false => out.extend_from_slice(format!("#{byte:02X}").as_bytes()),
+                }
+            }
+        }
+        Object::String(bytes, StringFormat::Literal) => {
+            out.push(b'(');
+            for &byte in bytes {
+                match byte {
+                    b'\\' | b'(' | b')' => out.extend_from_slice(&[b'\\', byte]),
+                    // Readers would turn a raw carriage return into a newline.
+                    b'\r' => out.extend_from_slice(b"\\r"),
+                    byte => out.push(byte),
+                }
+            }
+            out.push(b')');
+        }
+        Object::String(bytes, StringFormat::Hexadecimal) => {
+            out.push(b'<');
+            for byte in bytes {
+                out.extend_from_slice(format!("{byte:02X}").as_bytes());
+            }
+            out.push(b'>');
+        }
+        Object::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                write(out, item)?;
+            }
+            out.push(b']');
+        }
+        Object::Dictionary(dict) => write_dictionary(out, dict)?,
+        Object::Reference((id, generation)) => {
+            out.extend_from_slice(format!("{id} {generation} R").as_bytes())
+        }
+        Object::Stream(_) => return Err("can't update streams".to_string()),
+    }
+    Ok(())
+}
+
+fn write_dictionary(out: &mut Vec<u8>, dict: &Dictionary) -> Result<(), String> {
+    out.extend_from_slice(b"<<");
+    for (key, value) in dict.iter() {
+        out.push(b' ');
+        write(out, &Object::Name(key.clone()))?;
+        out.push(b' ');
+        write(out, value)?;
+    }
+    out.extend_from_slice(b" >>");
+    Ok(())
+}
+
+/// Where the last cross-reference section starts, according to the
+/// `startxref` at the end of the file.
+fn startxref(pdf: &[u8]) -> Option<usize> {
+    let tail = &pdf[pdf.len().saturating_sub(1024)..];
+    let keyword = b"startxref";
+    let start = tail.windows(keyword.len()).rposition(|w| w == keyword)?;
+    let rest = &tail[start + keyword.len()..];
+    let digits = (rest.iter())
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take_while(|b| b.is_ascii_digit())
+        .map(|&b| char::from(b))
+        .collect::<String>();
+    digits.parse().ok()
+}
+
+/// A PDF with objects appended to it. The cross-reference section and trailer
+/// are added by [`Update::finish`].
+pub struct Update {
+    pdf: Vec<u8>,
+    /// Offset of the previous cross-reference section.
+    prev: usize,
+    offsets: BTreeMap<ObjectId, usize>,
+}
+
+impl Update {
+    pub fn new(pdf: &[u8]) -> Result<Self, String> {
+        let prev = startxref(pdf).ok_or("cross-reference offset missing")?;
+        let mut pdf = pdf.to_vec();
+        if !pdf.ends_with(b"\n") {
+            pdf.push(b'\n');
+        }
+        Ok(Self {
+            pdf,
+            prev,
+            offsets: BTreeMap::new(),
+        })
+    }
+
+    /// Append an object, replacing any previous object with the same id.
+    pub fn write_object(&mut self, id: ObjectId, object: &Object) -> Result<(), String> {
+        let mut bytes = vec![];
+        write(&mut bytes, object)?;
+        self.write_raw(id, &bytes);
+        Ok(())
+    }
+
+    /// Append an object serialized by the caller, returning the offset its
+    /// serialization starts at.
+    pub fn write_raw(&mut self, id: ObjectId, bytes: &[u8]) -> usize {
+        self.offsets.insert(id, self.pdf.len());
+        let header = format!("{} {} obj\n", id.0, id.1);
+        self.pdf.extend_from_slice(header.as_bytes());
+        let start = self.pdf.len();
+        self.pdf.extend_from_slice(bytes);
+        self.pdf.extend_from_slice(b"\nendobj\n");
+        start
+    }
+
+    /// Add the cross-reference section for the appended objects and a trailer
+    /// based on the document's previous one.
+    ///
+    /// The section is always a table, which readers also accept after a
+    /// cross-reference stream.
+    pub fn finish(mut self, trailer: &Dictionary) -> Result<Vec<u8>, String> {
+        let xref = self.pdf.len();
+        self.pdf.extend_from_slice(b"xref\n");
+
+        // Consecutive object numbers share a subsection.
+        let ids = self.offsets.keys().copied().collect::<Vec<_>>();
+        for run in ids.chunk_by(|a, b| a.0 + 1 == b.0) {
+            let header = format!("{} {}\n", run[0].0, run.len());
+            self.pdf.extend_from_slice(header.as_bytes());
+            for id in run {
+                let entry = format!("{:010} {:05} n\r\n", self.offsets[id], id.1);
+                self.pdf.extend_from_slice(entry.as_bytes());
+            }
+        }
+
+        let size = (trailer.get(b"Size").and_then(Object::as_i64)).unwrap_or(0);
+        let max_id = ids.last().map_or(0, |id| id.0);
+        let mut new_trailer = Dictionary::new();
+        new_trailer.set("Size", size.max(i64::from(max_id) + 1));
+        for key in [&b"Root"[..], b"Info", b"ID"] {
+            if let Ok(value) = trailer.get(key) {
+                new_trailer.set(key, value.clone());
+            }
+        }
+        new_trailer.set("Prev", self.prev as i64);
+
+        self.pdf.extend_from_slice(b"trailer\n");
+        write_dictionary(&mut self.pdf, &new_trailer)?;
+        let end = format!("\nstartxref\n{xref}\n%%EOF\n");
+        self.pdf.extend_from_slice(end.as_bytes());
+        Ok(self.pdf)
+    }
+}
@@ -0,0 +1,56 @@
+//! Fixtures shared by the tests.
+
+// Not every test uses every fixture.
+#![allow(dead_code)]
+
+use arbeitszeitdokumentationsgenerator::render::{
+    self, Entry, Format, Language, Note, Templates, Timesheet, WorkingArea,
+};
+use jiff::tz::TimeZone;
+
+/// A month with a bit of everything: sorting, rests, notes and a carry.
+pub fn timesheet(language: Language) -> Timesheet {
+    let entry = |task: &str, day, start: &str, end: &str, rest: Option<&str>, note| Entry {
+        task: task.to_string(),
+        day,
+        start: start.to_string(),
+        end: end.to_string(),
+        rest: rest.map(str::to_string),
+        note,
+    };
+
+    Timesheet {
+        name: "McStudentface, Student".to_string(),
+        staff_id: "1337420".to_string(),
+        department: "Institut für Informatik".to_string(),
+        working_area: WorkingArea::Unibereich,
+        monthly_hours: 40,
+        hourly_wage: "14.09".to_string(),
+        validate: true,
+        sort: true,
+        carry_prev_month: Some("01:30".to_string()),
+        year: 2024,
+        month: 1,
+        holiday_state: "BW".to_string(),
+        date_signature: true,
+        signature_date: None,
+        signature: None,
+        approval: None,
+        language,
+        entries: vec![
+            entry("Tutorium", 16, "14:00", "16:00", None, None),
+            entry("Korrektur", 9, "10:00", "16:30", Some("00:30"), None),
+            entry("Urlaub", 22, "10:00", "14:00", None, Some(Note::Urlaub)),
+            entry("Vorbereitung", 10, "09:00", "11:00", None, None),
+        ],
+    }
+}
+
+/// Render with the built-in default template, dated to a fixed day.
+pub fn render(timesheet: &Timesheet, format: Format) -> Vec<u8> {
+    let templates = Templates::builtin();
+    let time_zone = TimeZone::get("Europe/Berlin").unwrap();
+    let now = render::now(time_zone, Some("2024-02-01")).unwrap();
+    render::render(&*templates.default(), timesheet.clone(), format, now)
+        .unwrap_or_else(|errors| panic!("rendering failed: {}", errors.join("\n")))
+}
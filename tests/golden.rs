@@ -0,0 +1,76 @@
+//! Rendering the same time sheet with the same fonts must always produce the
+//! same bytes. The expected PDFs in `tests/golden/` depend on the installed
+//! fonts, see the README for the ones they were generated with. After changing
+//! the template or the fonts, regenerate them with
+//! `ABZDOKGEN_BLESS=1 cargo test --test golden` and check them by eye.
+
+mod common;
+
+use std::{env, fs, path::PathBuf};
+
+use arbeitszeitdokumentationsgenerator::render::{Format, Language, Standard, Timesheet};
+
+fn check(name: &str, timesheet: Timesheet, format: Format) {
+    let first = common::render(&timesheet, format);
+    let second = common::render(&timesheet, format);
+    // Comparing with assert_eq! would print both PDFs byte by byte.
+    assert!(
+        first == second,
+        "{name}: rendering twice produced different bytes"
+    );
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.pdf"));
+    if env::var_os("ABZDOKGEN_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &first).unwrap();
+        return;
+    }
+
+    let expected = fs::read(&path).unwrap_or_else(|error| {
+        panic!(
+            "{name}: failed to read {}: {error}, run with ABZDOKGEN_BLESS=1 to create it",
+            path.display()
+        )
+    });
+    assert!(
+        first == expected,
+        "{name}: output differs from {}",
+        path.display()
+    );
+}
+
+#[test]
+fn german() {
+    let format = Format::Pdf { standard: None };
+    check("german", common::timesheet(Language::German), format);
+}
+
+#[test]
+fn english() {
+    let format = Format::Pdf { standard: None };
+    check("english", common::timesheet(Language::English), format);
+}
+
+#[test]
+fn bilingual() {
+    let format = Format::Pdf { standard: None };
+    check("bilingual", common::timesheet(Language::Bilingual), format);
+}
+
+#[test]
+fn pdf_a_2b() {
+    let format = Format::Pdf {
+        standard: Some(Standard::A2b),
+    };
+    check("pdf_a_2b", common::timesheet(Language::German), format);
+}
+
+#[test]
+fn pdf_a_3b() {
+    let format = Format::Pdf {
+        standard: Some(Standard::A3b),
+    };
+    check("pdf_a_3b", common::timesheet(Language::German), format);
+}
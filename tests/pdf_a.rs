@@ -0,0 +1,102 @@
+//! Check the parts of PDF/A-2b and PDF/A-3b that depend on how we call typst,
+//! not a full validation of the standards.
+
+mod common;
+
+use arbeitszeitdokumentationsgenerator::render::{self, Format, Language, Standard};
+use lopdf::{Document, Object};
+
+fn render(standard: Standard) -> Vec<u8> {
+    let timesheet = common::timesheet(Language::German);
+    let format = Format::Pdf {
+        standard: Some(standard),
+    };
+    common::render(&timesheet, format)
+}
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).unwrap().1
+}
+
+/// Text strings are either UTF-16 with a byte order mark or, for plain ASCII,
+/// single bytes.
+fn text(object: &Object) -> String {
+    let bytes = object.as_str().unwrap();
+    match bytes.strip_prefix(b"\xfe\xff") {
+        Some(utf16) => {
+            let units = (utf16.chunks_exact(2))
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16(&units).unwrap()
+        }
+        None => bytes.iter().map(|b| char::from(*b)).collect(),
+    }
+}
+
+fn xmp(doc: &Document) -> String {
+    let catalog = doc.catalog().unwrap();
+    let metadata = resolve(doc, catalog.get(b"Metadata").unwrap());
+    let stream = metadata.as_stream().unwrap();
+    let content = (stream.decompressed_content()).unwrap_or_else(|_| stream.content.clone());
+    String::from_utf8(content).unwrap()
+}
+
+/// XMP allows properties both as elements and as attributes.
+fn has_xmp_property(xmp: &str, name: &str, value: &str) -> bool {
+    xmp.contains(&format!("<{name}>{value}</{name}>"))
+        || xmp.contains(&format!("{name}=\"{value}\""))
+}
+
+fn check_common(doc: &Document) {
+    let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+    assert_eq!(id.len(), 2, "/ID must contain two strings");
+    assert!(id.iter().all(|part| part.as_str().is_ok()));
+
+    let info = resolve(doc, doc.trailer.get(b"Info").unwrap());
+    let info = info.as_dict().unwrap();
+    assert_eq!(
+        text(info.get(b"Title").unwrap()),
+        "Arbeitszeitdokumentation"
+    );
+    assert_eq!(text(info.get(b"Author").unwrap()), "McStudentface, Student");
+    assert_eq!(text(info.get(b"Subject").unwrap()), "01/2024");
+}
+
+fn has_embedded_files(doc: &Document) -> bool {
+    let catalog = doc.catalog().unwrap();
+    let Ok(names) = catalog.get(b"Names") else {
+        return false;
+    };
+    resolve(doc, names).as_dict().unwrap().has(b"EmbeddedFiles")
+}
+
+#[test]
+fn pdf_a_2b() {
+    let pdf = render(Standard::A2b);
+    let doc = Document::load_mem(&pdf).unwrap();
+    check_common(&doc);
+
+    let xmp = xmp(&doc);
+    assert!(has_xmp_property(&xmp, "pdfaid:part", "2"), "{xmp}");
+    assert!(has_xmp_property(&xmp, "pdfaid:conformance", "B"), "{xmp}");
+
+    // PDF/A-2 only allows embedding other PDF/A files.
+    assert!(!has_embedded_files(&doc));
+}
+
+#[test]
+fn pdf_a_3b() {
+    let pdf = render(Standard::A3b);
+    let doc = Document::load_mem(&pdf).unwrap();
+    check_common(&doc);
+
+    let xmp = xmp(&doc);
+    assert!(has_xmp_property(&xmp, "pdfaid:part", "3"), "{xmp}");
+    assert!(has_xmp_property(&xmp, "pdfaid:conformance", "B"), "{xmp}");
+
+    // The source data is attached and can be imported again.
+    assert!(has_embedded_files(&doc));
+    let extracted = render::extract(&pdf).unwrap();
+    assert_eq!(extracted.name, "McStudentface, Student");
+    assert_eq!(extracted.entries.len(), 4);
+}
@@ -0,0 +1,106 @@
+//! Saving profiles, as far as it doesn't depend on the stored data.
+
+use arbeitszeitdokumentationsgenerator::{
+    config::{Config, Endpoints, Profiles},
+    endpoints::{self, AppState},
+};
+use axum::{
+    body::Body,
+    http::{header, Request, Response, StatusCode},
+    Router,
+};
+use tower::ServiceExt;
+
+const FORM: &str = "name=McStudentface%2C+Student&staff_id=1234567&working_area=UB\
+                    &department=Institut+f%C3%BCr+Informatik&monthly_hours=40&hourly_wage=14.09";
+
+fn app(max_created_per_hour: u32, trust_forwarded_headers: bool) -> Router {
+    let config = Config {
+        trust_forwarded_headers,
+        endpoints: Endpoints {
+            profiles: true,
+            ..Endpoints::default()
+        },
+        profiles: Profiles {
+            database: ":memory:".into(),
+            max_created_per_hour,
+        },
+        ..Config::default()
+    };
+    endpoints::router(AppState::new(config).unwrap())
+}
+
+async fn save(app: &Router, headers: &[(&str, &str)]) -> Response<Body> {
+    let mut request =
+        Request::post("/profile").header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let request = request.body(Body::from(FORM)).unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+fn cookie(response: &Response<Body>) -> &str {
+    response.headers()[header::SET_COOKIE].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn creating_is_limited() {
+    let app = app(1, false);
+
+    let response = save(&app, &[]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let token = cookie(&response).split(';').next().unwrap().to_string();
+
+    let response = save(&app, &[]).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Updating an existing profile doesn't create a new one.
+    let response = save(&app, &[("cookie", &token)]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn creating_is_limited_per_client() {
+    let first = [("x-forwarded-for", "192.0.2.1, 198.51.100.1")];
+    let spoofed = [("x-forwarded-for", "192.0.2.1, 198.51.100.2")];
+    let same_network = [("x-forwarded-for", "2001:db8::1")];
+    let other_network = [("x-forwarded-for", "2001:db8:0:1::1")];
+
+    let trusted = app(1, true);
+    let response = save(&trusted, &first).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = save(&trusted, &first).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    // Only the address added by the proxy counts.
+    let response = save(&trusted, &spoofed).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = save(&trusted, &same_network).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = save(&trusted, &[("x-forwarded-for", "2001:db8::2")]).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = save(&trusted, &other_network).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // Without trusting the proxy, everybody shares the limit.
+    let untrusted = app(1, false);
+    let response = save(&untrusted, &first).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = save(&untrusted, &spoofed).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn secure_cookie_over_https() {
+    let https = [("x-forwarded-proto", "https")];
+
+    let response = save(&app(10, false), &[]).await;
+    assert!(!cookie(&response).contains("Secure"));
+
+    let response = save(&app(10, false), &https).await;
+    assert!(!cookie(&response).contains("Secure"));
+
+    let response = save(&app(10, true), &https).await;
+    assert!(cookie(&response).ends_with("; Secure"));
+}
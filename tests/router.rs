@@ -0,0 +1,97 @@
+//! Which paths the server answers, with and without a base path.
+
+use arbeitszeitdokumentationsgenerator::{
+    config::Config,
+    endpoints::{self, AppState},
+};
+use axum::{
+    body::Body,
+    http::{header, Request, Response, StatusCode},
+};
+use tower::ServiceExt;
+
+fn config(base_path: &str, trust_forwarded_headers: bool) -> Config {
+    Config {
+        base_path: base_path.to_string(),
+        trust_forwarded_headers,
+        ..Config::default()
+    }
+}
+
+async fn get(config: Config, path: &str, headers: &[(&str, &str)]) -> Response<Body> {
+    let mut request = Request::get(path);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let app = endpoints::router(AppState::new(config).unwrap());
+    app.oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn location(response: &Response<Body>) -> &str {
+    response.headers()[header::LOCATION].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn without_base_path() {
+    let response = get(config("", false), "/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get(config("", false), "/tsg/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn with_base_path() {
+    let response = get(config("/abzdokgen", false), "/abzdokgen/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get(config("/abzdokgen", false), "/abzdokgen/tsg/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get(config("/abzdokgen", false), "/", &[]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = get(config("/abzdokgen", false), "/tsg/", &[]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn redirect_to_index() {
+    let response = get(config("/abzdokgen", false), "/abzdokgen", &[]).await;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(location(&response), "/abzdokgen/");
+}
+
+#[tokio::test]
+async fn forwarded_prefix_needs_trust() {
+    let headers = [("x-forwarded-prefix", "/proxy")];
+
+    let response = get(config("/abzdokgen", false), "/abzdokgen", &headers).await;
+    assert_eq!(location(&response), "/abzdokgen/");
+
+    let response = get(config("/abzdokgen", true), "/abzdokgen", &headers).await;
+    assert_eq!(location(&response), "/proxy/abzdokgen/");
+}
+
+#[tokio::test]
+async fn forwarded_prefix_must_be_a_path() {
+    for prefix in [
+        "//evil.example",
+        "https://evil.example",
+        "/a b",
+        "/a\"b",
+        "/a;b",
+        "a",
+    ] {
+        let headers = [("x-forwarded-prefix", prefix)];
+        let response = get(config("/abzdokgen", true), "/abzdokgen", &headers).await;
+        assert_eq!(location(&response), "/abzdokgen/", "prefix {prefix:?}");
+    }
+
+    // A trailing slash is fine.
+    let headers = [("x-forwarded-prefix", "/proxy/")];
+    let response = get(config("/abzdokgen", true), "/abzdokgen", &headers).await;
+    assert_eq!(location(&response), "/proxy/abzdokgen/");
+}
@@ -0,0 +1,115 @@
+//! Signing and verifying, including documents that were signed before and
+//! documents signed by other software.
+
+mod common;
+
+use arbeitszeitdokumentationsgenerator::{
+    render::{Format, Language},
+    sign::{self, Identity, Verification},
+};
+use lopdf::Document;
+
+/// A self-signed certificate for "Test Supervisor", password "test".
+const SIGNER: &[u8] = include_bytes!("fixtures/signer.p12");
+
+/// A minimal PDF signed with `openssl cms -sign -cades` by the same key.
+const OPENSSL_SIGNED: &[u8] = include_bytes!("fixtures/openssl-signed.pdf");
+
+fn identity() -> Identity {
+    Identity::from_pkcs12(SIGNER, "test").unwrap()
+}
+
+fn pdf() -> Vec<u8> {
+    let timesheet = common::timesheet(Language::German);
+    common::render(&timesheet, Format::Pdf { standard: None })
+}
+
+fn verify(pdf: &[u8]) -> Vec<Verification> {
+    sign::verify_pdf(pdf).unwrap()
+}
+
+fn fields(pdf: &[u8]) -> usize {
+    let doc = Document::load_mem(pdf).unwrap();
+    let form = doc.catalog().unwrap().get(b"AcroForm").unwrap();
+    let form = doc.dereference(form).unwrap().1.as_dict().unwrap();
+    let fields = doc.dereference(form.get(b"Fields").unwrap()).unwrap().1;
+    fields.as_array().unwrap().len()
+}
+
+#[test]
+fn sign_and_verify() {
+    let pdf = pdf();
+    let signed = sign::sign_pdf(&pdf, &identity()).unwrap();
+    assert!(signed.starts_with(&pdf), "signing must only append");
+
+    let verifications = verify(&signed);
+    assert_eq!(verifications.len(), 1);
+    let verification = &verifications[0];
+    assert!(verification.intact);
+    assert!(verification.covers_whole_document);
+    assert_eq!(verification.signer.as_deref(), Some("Test Supervisor"));
+}
+
+#[test]
+fn detect_changes() {
+    let mut signed = sign::sign_pdf(&pdf(), &identity()).unwrap();
+
+    // The signing time is covered by the signature.
+    let time = signed.windows(6).rposition(|w| w == b"/M (D:").unwrap() + 6;
+    signed[time] = if signed[time] == b'1' { b'2' } else { b'1' };
+    assert!(!verify(&signed)[0].intact);
+}
+
+#[test]
+fn detect_appended_data() {
+    let mut signed = sign::sign_pdf(&pdf(), &identity()).unwrap();
+    signed.extend_from_slice(b"% appended\n");
+
+    let verification = &verify(&signed)[0];
+    assert!(verification.intact);
+    assert!(!verification.covers_whole_document);
+}
+
+#[test]
+fn sign_twice() {
+    let once = sign::sign_pdf(&pdf(), &identity()).unwrap();
+    let twice = sign::sign_pdf(&once, &identity()).unwrap();
+    assert!(twice.starts_with(&once), "signing must only append");
+    assert_eq!(fields(&twice), 2);
+
+    let verifications = verify(&twice);
+    assert_eq!(verifications.len(), 2);
+    assert!(verifications.iter().all(|v| v.intact));
+    assert!(!verifications[0].covers_whole_document);
+    assert!(verifications[1].covers_whole_document);
+}
+
+#[test]
+fn verify_openssl_signature() {
+    let verifications = verify(OPENSSL_SIGNED);
+    assert_eq!(verifications.len(), 1);
+    let verification = &verifications[0];
+    assert!(verification.intact);
+    assert!(verification.covers_whole_document);
+    assert_eq!(verification.signer.as_deref(), Some("Test Supervisor"));
+    assert_eq!(
+        verification.signed_at.as_deref(),
+        Some("D:20240201120000+00'00'")
+    );
+}
+
+#[test]
+fn sign_openssl_signed() {
+    let signed = sign::sign_pdf(OPENSSL_SIGNED, &identity()).unwrap();
+    assert!(
+        signed.starts_with(OPENSSL_SIGNED),
+        "signing must only append"
+    );
+    assert_eq!(fields(&signed), 2);
+
+    let verifications = verify(&signed);
+    assert_eq!(verifications.len(), 2);
+    assert!(verifications.iter().all(|v| v.intact));
+    assert!(!verifications[0].covers_whole_document);
+    assert!(verifications[1].covers_whole_document);
+}